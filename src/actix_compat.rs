@@ -0,0 +1,41 @@
+use std::future::{ready, Ready};
+use actix_web::dev::Payload;
+use actix_web::error::ErrorInternalServerError;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest};
+use Context;
+
+/// Extracts the context stored in the request's extensions (e.g. by a middleware) in actix-web
+/// handlers. Requests without a stored context are rejected with a `500 Internal Server Error`.
+/// Requires the `actix` feature.
+impl FromRequest for Context {
+    type Error = Error;
+    type Future = Ready<Result<Context, Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let ctx = req.extensions().get::<Context>().cloned();
+        ready(ctx.ok_or_else(|| {
+            ErrorInternalServerError("no context stored in the request extensions")
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::test::TestRequest;
+    use actix_web::{FromRequest, HttpMessage};
+    use {background, with_value, Context};
+
+    #[test]
+    fn extractor_test() {
+        let req = TestRequest::default().to_http_request();
+        req.extensions_mut().insert(with_value(background(), 42));
+        let ctx = Context::extract(&req).into_inner().unwrap();
+        assert_eq!(ctx.value(), Some(42));
+    }
+
+    #[test]
+    fn extractor_missing_test() {
+        let req = TestRequest::default().to_http_request();
+        assert!(Context::extract(&req).into_inner().is_err());
+    }
+}
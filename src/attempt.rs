@@ -0,0 +1,65 @@
+use {Context, WithValue};
+
+/// The number of the current attempt (see `with_attempt`), wrapped into its own type since a
+/// bare `u32` value might as well be a page size or a user id.
+#[derive(Clone, Copy)]
+struct Attempt(u32);
+
+/// Returns a copy of parent with the given attempt number associated to it, e.g. to record
+/// retries in downstream logs and metrics.
+///
+/// # Examples
+///
+/// ```
+/// use ctx::{with_attempt, background};
+///
+/// let ctx = with_attempt(background(), 1);
+/// assert_eq!(ctx.attempt(), 1);
+/// let ctx = ctx.next_attempt();
+/// assert_eq!(ctx.attempt(), 2);
+/// ```
+pub fn with_attempt(parent: Context, attempt: u32) -> Context {
+    Context::new(WithValue::new(parent, Attempt(attempt)))
+}
+
+impl Context {
+    /// Returns the attempt number associated to the context via `with_attempt` or
+    /// `next_attempt`, or `0` if there is none.
+    pub fn attempt(&self) -> u32 {
+        self.value::<Attempt>().map_or(0, |attempt| attempt.0)
+    }
+
+    /// Returns a copy of the context with its attempt number incremented by one.
+    pub fn next_attempt(self) -> Context {
+        let attempt = self.attempt().saturating_add(1);
+        with_attempt(self, attempt)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use attempt::with_attempt;
+    use {background, with_cancel, with_value};
+
+    #[test]
+    fn next_attempt_test() {
+        let ctx = background();
+        assert_eq!(ctx.attempt(), 0);
+
+        let ctx = ctx.next_attempt();
+        assert_eq!(ctx.attempt(), 1);
+        let (ctx, _) = with_cancel(ctx);
+        let ctx = ctx.next_attempt().next_attempt();
+        assert_eq!(ctx.attempt(), 3);
+
+        let ctx = with_attempt(ctx, 7);
+        assert_eq!(ctx.next_attempt().attempt(), 8);
+    }
+
+    #[test]
+    fn no_collision_test() {
+        let ctx = with_value(background(), 5u32).next_attempt();
+        assert_eq!(ctx.value(), Some(5u32));
+        assert_eq!(ctx.attempt(), 1);
+    }
+}
@@ -0,0 +1,63 @@
+use std::future::{ready, Future};
+use axum_core::extract::FromRequestParts;
+use http::request::Parts;
+use http::StatusCode;
+use Context;
+
+/// Extracts the context stored in the request's extensions (e.g. by `Context::from_request` or
+/// a middleware) in axum handlers. Requests without a stored context are rejected with a
+/// `500 Internal Server Error`. Requires the `axum` feature.
+impl<S> FromRequestParts<S> for Context
+where
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, &'static str);
+
+    fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+        ready(parts.extensions.get::<Context>().cloned().ok_or((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "no context stored in the request extensions",
+        )))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context as TaskContext, Poll, Waker};
+    use axum_core::extract::FromRequestParts;
+    use http::{Request, StatusCode};
+    use {background, with_value, Context};
+
+    fn extract(req: Request<()>) -> Result<Context, (StatusCode, &'static str)> {
+        let (mut parts, _) = req.into_parts();
+        let future = pin!(Context::from_request_parts(&mut parts, &()));
+        match future.poll(&mut TaskContext::from_waker(Waker::noop())) {
+            Poll::Ready(result) => result,
+            Poll::Pending => panic!("extractor must be ready immediately"),
+        }
+    }
+
+    #[test]
+    fn extractor_test() {
+        let req = Request::builder()
+            .extension(with_value(background(), 42))
+            .body(())
+            .unwrap();
+        let ctx = extract(req).unwrap();
+        assert_eq!(ctx.value(), Some(42));
+    }
+
+    #[test]
+    fn extractor_missing_test() {
+        let req = Request::builder().body(()).unwrap();
+        match extract(req) {
+            Err((status, _)) => assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR),
+            Ok(_) => panic!("expected the request to be rejected"),
+        }
+    }
+}
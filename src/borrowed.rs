@@ -0,0 +1,201 @@
+use std::any::{Any, TypeId};
+use std::marker::PhantomData;
+use std::time::Instant;
+use {Context, Done};
+
+/// A lightweight derivation of a borrowed context, which can carry additional values without
+/// taking ownership of (and thus without cloning or moving) the parent context. It is meant for
+/// synchronous middleware, which does not need to move the context across threads or into
+/// futures.
+///
+/// Besides owned values, it can carry borrowed values of non-`'static` types (see `with_ref`),
+/// which is why it cannot outlive the values it borrows, and cannot be sent across threads.
+pub struct BorrowedCtx<'a> {
+    parent: &'a Context,
+    values: Vec<Box<dyn Any>>,
+    refs: Vec<(TypeId, *const ())>,
+    /// Keeps `'a` invariant, so that borrowed values are retrieved with exactly the lifetime they
+    /// have been stored with.
+    lifetime: PhantomData<fn(&'a ()) -> &'a ()>,
+}
+
+/// A `BorrowedCtx` that is used to carry borrowed values (see `BorrowedCtx::with_ref`).
+pub type ScopedContext<'a> = BorrowedCtx<'a>;
+
+/// Identifies a borrowed value carried by a `BorrowedCtx`. Since the borrowed value's type might
+/// not be `'static` itself (e.g. `RequestHeaders<'b>`), values are identified by a separate
+/// `'static` key type instead.
+///
+/// # Examples
+///
+/// ```
+/// use ctx::{borrow_and_derive, background, ScopedKey};
+///
+/// struct RequestHeaders<'b> {
+///     accept: &'b str,
+/// }
+///
+/// struct Headers;
+///
+/// impl ScopedKey for Headers {
+///     type Value<'b> = RequestHeaders<'b>;
+/// }
+///
+/// let raw = String::from("*/*");
+/// let headers = RequestHeaders { accept: &raw };
+/// let ctx = background();
+/// let scoped = borrow_and_derive(&ctx).with_ref::<Headers>(&headers);
+/// assert_eq!(scoped.get_ref::<Headers>().unwrap().accept, "*/*");
+/// ```
+pub trait ScopedKey: 'static {
+    /// The type of the borrowed value identified by the key.
+    type Value<'b>: 'b;
+}
+
+/// Returns a derivation of the given context, which holds a reference to it instead of owning it.
+///
+/// # Examples
+///
+/// ```
+/// use ctx::{borrow_and_derive, with_value, background};
+///
+/// let ctx = with_value(background(), 42);
+/// let derived = borrow_and_derive(&ctx).with_value("user");
+/// assert_eq!(derived.value(), Some(42));
+/// assert_eq!(derived.value(), Some("user"));
+/// ```
+pub fn borrow_and_derive(parent: &Context) -> BorrowedCtx<'_> {
+    BorrowedCtx {
+        parent,
+        values: Vec::new(),
+        refs: Vec::new(),
+        lifetime: PhantomData,
+    }
+}
+
+impl<'a> BorrowedCtx<'a> {
+    /// Returns the derivation with the given value associated to it, shadowing values of the same
+    /// type of both earlier derivations and the parent context.
+    pub fn with_value<V>(mut self, val: V) -> Self
+    where
+        V: Any,
+    {
+        self.values.push(Box::new(val));
+        self
+    }
+
+    /// Returns the derivation with the given borrowed value associated to it, identified by the
+    /// key `K`, shadowing values of the same key of earlier derivations.
+    pub fn with_ref<K>(mut self, val: &'a K::Value<'a>) -> Self
+    where
+        K: ScopedKey,
+    {
+        let ptr = val as *const K::Value<'a> as *const ();
+        self.refs.push((TypeId::of::<K>(), ptr));
+        self
+    }
+
+    /// Returns the closest borrowed value identified by the key `K`, borrowed for the lifetime of
+    /// the derivation.
+    pub fn get_ref<K>(&self) -> Option<&'a K::Value<'a>>
+    where
+        K: ScopedKey,
+    {
+        let key = TypeId::of::<K>();
+        self.refs
+            .iter()
+            .rev()
+            .find(|&&(id, _)| id == key)
+            // SAFETY: the pointer has been created by `with_ref::<K>` from a `&'a K::Value<'a>`,
+            // and `'a` is invariant, so that it is cast back to exactly the same type.
+            .map(|&(_, ptr)| unsafe { &*(ptr as *const K::Value<'a>) })
+    }
+
+    /// Returns the borrowed parent context.
+    pub fn parent(&self) -> &'a Context {
+        self.parent
+    }
+
+    pub fn value<T>(&self) -> Option<T>
+    where
+        T: Any + Clone,
+    {
+        self.value_ref().cloned()
+    }
+
+    pub fn value_ref<T>(&self) -> Option<&T>
+    where
+        T: Any,
+    {
+        self.values
+            .iter()
+            .rev()
+            .find_map(|val| val.downcast_ref::<T>())
+            .or_else(|| self.parent.value_ref())
+    }
+
+    /// Returns the effective deadline of the parent context.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.parent.deadline()
+    }
+
+    /// Returns a future that resolves once the parent context is done (see `Context::done`).
+    pub fn done(&self) -> Done {
+        self.parent.done()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use borrowed::{borrow_and_derive, ScopedKey};
+    use {background, with_timeout, with_value};
+
+    #[test]
+    fn borrow_and_derive_test() {
+        let (ctx, _) = with_timeout(background(), Duration::from_secs(10));
+        let ctx = with_value(ctx, 42);
+        let ctx = with_value(ctx, "request");
+
+        {
+            let derived = borrow_and_derive(&ctx).with_value("middleware").with_value(1.0);
+            assert_eq!(derived.value(), Some(42));
+            assert_eq!(derived.value(), Some("middleware"));
+            assert_eq!(derived.value(), Some(1.0));
+            assert_eq!(derived.deadline(), ctx.deadline());
+            assert!(derived.value::<u8>().is_none());
+        }
+
+        // the parent is untouched by the derivation
+        assert_eq!(ctx.value(), Some("request"));
+        assert_eq!(ctx.value::<f64>(), None);
+    }
+
+    #[test]
+    fn with_ref_test() {
+        struct RequestHeaders<'b> {
+            accept: &'b str,
+        }
+
+        struct Headers;
+
+        impl ScopedKey for Headers {
+            type Value<'b> = RequestHeaders<'b>;
+        }
+
+        fn handle(headers: &RequestHeaders<'_>) -> Option<String> {
+            let ctx = with_value(background(), 42);
+            let scoped = borrow_and_derive(&ctx).with_ref::<Headers>(headers);
+            assert_eq!(scoped.value_ref::<i32>(), Some(&42));
+            scoped.get_ref::<Headers>().map(|h| h.accept.to_string())
+        }
+
+        let raw = String::from("text/html");
+        let headers = RequestHeaders { accept: &raw };
+        assert_eq!(handle(&headers), Some("text/html".to_string()));
+
+        let ctx = background();
+        let scoped = borrow_and_derive(&ctx);
+        assert!(scoped.get_ref::<Headers>().is_none());
+    }
+}
@@ -0,0 +1,282 @@
+use std::io::{self, Read, Write};
+use futures::{task, Future};
+use {Context, Done};
+
+/// An I/O object bound to a context. Created via `Context::bound_io`.
+///
+/// Once the context is canceled or its deadline is exceeded, all subsequent reads and writes fail
+/// with the `io::Error` the context error maps to (e.g. `io::ErrorKind::Interrupted` for
+/// cancelation and `io::ErrorKind::TimedOut` for an exceeded deadline).
+pub struct BoundIo<T> {
+    io: T,
+    done: Done,
+    #[cfg(feature = "tokio")]
+    waker: ::std::sync::Arc<tokio_io::WakerSlot>,
+}
+
+impl<T> BoundIo<T> {
+    /// Returns a reference to the underlying I/O object.
+    pub fn get_ref(&self) -> &T {
+        &self.io
+    }
+
+    /// Consumes the wrapper, returning the underlying I/O object.
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+
+    fn check(&self) -> io::Result<()> {
+        match self.done.check() {
+            Some(err) => Err(err.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Registers the current task to be notified once the context is done, if the given result
+    /// of an operation indicates that the task is going to wait for the I/O object.
+    fn register<R>(&mut self, result: io::Result<R>) -> io::Result<R> {
+        match result {
+            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock && task::is_in_task() => {
+                if let Err(err) = self.done.poll() {
+                    return Err(err.into());
+                }
+            }
+            _ => {}
+        }
+        result
+    }
+}
+
+impl<T: Read> Read for BoundIo<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.check()?;
+        let result = self.io.read(buf);
+        self.register(result)
+    }
+}
+
+impl<T: Write> Write for BoundIo<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.check()?;
+        let result = self.io.write(buf);
+        self.register(result)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.check()?;
+        let result = self.io.flush();
+        self.register(result)
+    }
+}
+
+#[cfg(feature = "tokio")]
+mod tokio_io {
+    use std::io;
+    use std::pin::Pin;
+    use std::sync::Mutex;
+    use std::task::{Context, Poll, Waker};
+    use futures::executor::{self, Notify, NotifyHandle};
+    use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+    use lock::lock;
+    use super::BoundIo;
+
+    /// Bridges the notifications of the (futures 0.1) `Done` future to the waker of the latest
+    /// poll. The slot is reused across polls, so that the signals do not accumulate a task per
+    /// poll.
+    #[derive(Default)]
+    pub(super) struct WakerSlot(Mutex<Option<Waker>>);
+
+    impl Notify for WakerSlot {
+        fn notify(&self, _id: usize) {
+            if let Some(waker) = lock(&self.0).take() {
+                waker.wake();
+            }
+        }
+    }
+
+    impl<T> BoundIo<T> {
+        /// Registers the task's waker to be woken once the context is done, if the given result
+        /// of an operation is pending.
+        fn register_waker<R>(
+            &mut self,
+            cx: &mut Context<'_>,
+            result: Poll<io::Result<R>>,
+        ) -> Poll<io::Result<R>> {
+            if result.is_pending() {
+                *lock(&self.waker.0) = Some(cx.waker().clone());
+                let notify = NotifyHandle::from(self.waker.clone());
+                if let Err(err) = executor::spawn(&mut self.done).poll_future_notify(&notify, 0) {
+                    return Poll::Ready(Err(err.into()));
+                }
+            }
+            result
+        }
+    }
+
+    impl<T: AsyncRead + Unpin> AsyncRead for BoundIo<T> {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            let this = self.get_mut();
+            this.check()?;
+            let result = Pin::new(&mut this.io).poll_read(cx, buf);
+            this.register_waker(cx, result)
+        }
+    }
+
+    impl<T: AsyncWrite + Unpin> AsyncWrite for BoundIo<T> {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            let this = self.get_mut();
+            this.check()?;
+            let result = Pin::new(&mut this.io).poll_write(cx, buf);
+            this.register_waker(cx, result)
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            let this = self.get_mut();
+            this.check()?;
+            let result = Pin::new(&mut this.io).poll_flush(cx);
+            this.register_waker(cx, result)
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+        }
+    }
+}
+
+impl Context {
+    /// Binds the given I/O object (implementing `Read`/`Write`, or tokio's
+    /// `AsyncRead`/`AsyncWrite` with the `tokio` feature enabled) to the context, so that reads
+    /// and writes fail once the context is done.
+    ///
+    /// The context is checked before each operation. Non-blocking operations that are waiting for
+    /// the I/O object (i.e. returned `WouldBlock` within a task, or `Pending`) are woken once the
+    /// context is done; blocking operations that are already in progress are not interrupted.
+    pub fn bound_io<T>(&self, io: T) -> BoundIo<T> {
+        BoundIo {
+            io,
+            done: self.done(),
+            #[cfg(feature = "tokio")]
+            waker: Default::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, Cursor, Read, Write};
+    use std::thread;
+    use std::time::Duration;
+    use futures::{Async, Future};
+    use futures::future::poll_fn;
+    use {background, with_cancel, with_timeout};
+
+    /// A reader that never has any data available.
+    struct Pending;
+
+    impl Read for Pending {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::WouldBlock.into())
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    impl ::tokio::io::AsyncRead for Pending {
+        fn poll_read(
+            self: ::std::pin::Pin<&mut Self>,
+            _: &mut ::std::task::Context<'_>,
+            _: &mut ::tokio::io::ReadBuf<'_>,
+        ) -> ::std::task::Poll<io::Result<()>> {
+            ::std::task::Poll::Pending
+        }
+    }
+
+    #[test]
+    fn bound_io_cancel_test() {
+        let (ctx, cancel) = with_cancel(background());
+        let mut io = ctx.bound_io(Cursor::new(b"hello".to_vec()));
+
+        let mut buf = [0; 2];
+        assert_eq!(io.read(&mut buf).unwrap(), 2);
+        assert_eq!(&buf, b"he");
+
+        cancel();
+        let err = io.read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+        let err = io.write(b"!").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+    }
+
+    #[test]
+    fn bound_io_deadline_test() {
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(10));
+        let mut io = ctx.bound_io(Vec::new());
+        thread::sleep(Duration::from_millis(20));
+
+        let err = io.write(b"hello").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(io.get_ref().is_empty());
+    }
+
+    #[test]
+    fn bound_io_pending_read_test() {
+        let (ctx, cancel) = with_cancel(background());
+        let mut io = ctx.bound_io(Pending);
+        let canceler = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            cancel();
+        });
+
+        let mut buf = [0; 2];
+        let read = poll_fn(|| match io.read(&mut buf) {
+            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => Ok(Async::NotReady),
+            Err(err) => Err(err),
+            Ok(n) => Ok(Async::Ready(n)),
+        });
+        assert_eq!(read.wait().unwrap_err().kind(), io::ErrorKind::Interrupted);
+        canceler.join().unwrap();
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn bound_io_pending_poll_read_test() {
+        use std::pin::Pin;
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::task::{Context as TaskContext, Poll, Wake, Waker};
+        use tokio::io::{AsyncRead, ReadBuf};
+
+        struct Flag(AtomicBool);
+
+        impl Wake for Flag {
+            fn wake(self: Arc<Self>) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let (ctx, cancel) = with_cancel(background());
+        let mut io = ctx.bound_io(Pending);
+        let woken = Arc::new(Flag(AtomicBool::new(false)));
+        let waker = Waker::from(woken.clone());
+        let mut cx = TaskContext::from_waker(&waker);
+
+        let mut buf = [0; 2];
+        let mut buf = ReadBuf::new(&mut buf);
+        assert!(Pin::new(&mut io).poll_read(&mut cx, &mut buf).is_pending());
+        assert!(Pin::new(&mut io).poll_read(&mut cx, &mut buf).is_pending());
+
+        cancel();
+        assert!(woken.0.load(Ordering::SeqCst));
+        match Pin::new(&mut io).poll_read(&mut cx, &mut buf) {
+            Poll::Ready(Err(err)) => assert_eq!(err.kind(), io::ErrorKind::Interrupted),
+            _ => panic!("expected the read to be aborted"),
+        }
+    }
+}
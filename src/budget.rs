@@ -0,0 +1,199 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use {Context, CancelFn, with_cancel, with_timeout};
+use lock::lock;
+
+type OverrunFn = Arc<dyn Fn(&PhaseUsage) + Send + Sync>;
+
+/// Allocates fractions of a context's remaining time to the phases of an operation and tracks
+/// how much time each phase actually consumed. Created via `Context::budget`.
+pub struct Budget {
+    ctx: Context,
+    total: Option<Duration>,
+    usage: Arc<Mutex<Vec<PhaseUsage>>>,
+    on_overrun: Option<OverrunFn>,
+}
+
+/// A phase of a `Budget`, created via `Budget::slice`. The time consumed by the phase is recorded
+/// once it is finished or dropped.
+pub struct Phase {
+    allotted: Option<Duration>,
+    started: Instant,
+    cancel: CancelFn,
+    usage: Arc<Mutex<Vec<PhaseUsage>>>,
+    on_overrun: Option<OverrunFn>,
+    finished: bool,
+}
+
+/// The time allotted to and consumed by a phase of a `Budget`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct PhaseUsage {
+    /// The time allotted to the phase, or `None` if the budget is unbounded.
+    pub allotted: Option<Duration>,
+    pub consumed: Duration,
+}
+
+impl PhaseUsage {
+    /// Returns whether the phase consumed more time than it was allotted.
+    pub fn overran(&self) -> bool {
+        self.allotted.is_some_and(|allotted| self.consumed > allotted)
+    }
+}
+
+impl Context {
+    /// Returns a budget of the time remaining until the context's deadline.
+    pub fn budget(&self) -> Budget {
+        Budget {
+            ctx: self.clone(),
+            total: self.deadline_remaining(),
+            usage: Arc::new(Mutex::new(Vec::new())),
+            on_overrun: None,
+        }
+    }
+}
+
+impl Budget {
+    /// Registers a callback, which is called whenever a phase finishes after consuming more time
+    /// than it was allotted.
+    pub fn on_overrun<F>(mut self, f: F) -> Self
+    where
+        F: 'static + Fn(&PhaseUsage) + Send + Sync,
+    {
+        self.on_overrun = Some(Arc::new(f));
+        self
+    }
+
+    /// Derives a context from the one the budget has been created from, with a timeout of the
+    /// given fraction (between `0.0` and `1.0`) of the total budget. If the budget is unbounded,
+    /// so is the returned context. Fractions outside of that range are clamped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fraction` is NaN.
+    pub fn slice(&self, fraction: f64) -> (Context, Phase) {
+        assert!(!fraction.is_nan(), "budget fraction must not be NaN");
+        let allotted = self
+            .total
+            .map(|total| total.mul_f64(fraction.clamp(0.0, 1.0)));
+        let (ctx, cancel) = match allotted {
+            Some(timeout) => with_timeout(self.ctx.clone(), timeout),
+            None => with_cancel(self.ctx.clone()),
+        };
+        let phase = Phase {
+            allotted,
+            started: Instant::now(),
+            cancel,
+            usage: self.usage.clone(),
+            on_overrun: self.on_overrun.clone(),
+            finished: false,
+        };
+        (ctx, phase)
+    }
+
+    /// Returns the usage of all finished phases in the order they finished.
+    pub fn usage(&self) -> Vec<PhaseUsage> {
+        lock(&self.usage).clone()
+    }
+}
+
+impl Phase {
+    /// Cancels the context of this phase.
+    pub fn cancel(&self) {
+        (self.cancel)()
+    }
+
+    /// Finishes the phase and returns its usage.
+    pub fn finish(mut self) -> PhaseUsage {
+        self.record()
+    }
+
+    fn record(&mut self) -> PhaseUsage {
+        self.finished = true;
+        let usage = PhaseUsage {
+            allotted: self.allotted,
+            consumed: self.started.elapsed(),
+        };
+        lock(&self.usage).push(usage);
+        if usage.overran() {
+            if let Some(ref on_overrun) = self.on_overrun {
+                on_overrun(&usage);
+            }
+        }
+        usage
+    }
+}
+
+impl Drop for Phase {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.record();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+    use {background, with_timeout};
+
+    #[test]
+    fn slice_test() {
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(100));
+        let budget = ctx.budget();
+
+        let (sub, phase) = budget.slice(0.5);
+        let remaining = sub.deadline_remaining().unwrap();
+        assert!(remaining <= Duration::from_millis(50));
+        assert!(remaining > Duration::from_millis(40));
+
+        let usage = phase.finish();
+        assert!(!usage.overran());
+        assert_eq!(budget.usage(), vec![usage]);
+    }
+
+    #[test]
+    fn overrun_test() {
+        let overruns = Arc::new(AtomicUsize::new(0));
+        let overruns_clone = overruns.clone();
+
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(100));
+        let budget = ctx.budget().on_overrun(move |_| {
+            overruns_clone.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let (_, phase) = budget.slice(0.1);
+        thread::sleep(Duration::from_millis(30));
+        drop(phase);
+
+        assert_eq!(overruns.load(Ordering::SeqCst), 1);
+        assert!(budget.usage()[0].overran());
+    }
+
+    #[test]
+    fn unbounded_test() {
+        let budget = background().budget();
+        let (ctx, phase) = budget.slice(0.5);
+        assert_eq!(ctx.deadline(), None);
+        assert_eq!(phase.finish().allotted, None);
+    }
+
+    #[test]
+    fn clamp_test() {
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(100));
+        let budget = ctx.budget();
+        let (_, phase) = budget.slice(f64::INFINITY);
+        assert_eq!(phase.finish().allotted, budget.total);
+        let (_, phase) = budget.slice(-1.0);
+        assert_eq!(phase.finish().allotted, Some(Duration::from_secs(0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "NaN")]
+    fn nan_test() {
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(100));
+        let _ = ctx.budget().slice(f64::NAN);
+    }
+}
@@ -0,0 +1,100 @@
+use std::any::Any;
+use std::time::Duration;
+use {CancelFn, Context, with_cancel, with_timeout, with_value};
+
+/// A builder deriving a context with several values and an optional timeout in one go. Created
+/// via `Context::builder`.
+pub struct ContextBuilder {
+    parent: Context,
+    values: Vec<Box<dyn FnOnce(Context) -> Context>>,
+    timeout: Option<Duration>,
+}
+
+impl ContextBuilder {
+    /// Associates the given value to the built context (see `with_value`). Values are added in
+    /// the order of the calls, i.e. later values shadow earlier ones of the same type.
+    pub fn value<V>(mut self, val: V) -> Self
+    where
+        V: Any + Send + Sync,
+    {
+        self.values.push(Box::new(move |parent| with_value(parent, val)));
+        self
+    }
+
+    /// Sets the timeout of the built context (see `with_timeout`), which starts once `build` is
+    /// called. Calling it again replaces the previous timeout.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Builds the context, together with a function canceling it.
+    pub fn build(self) -> (Context, CancelFn) {
+        let ctx = self
+            .values
+            .into_iter()
+            .fold(self.parent, |parent, with_value| with_value(parent));
+        match self.timeout {
+            Some(timeout) => with_timeout(ctx, timeout),
+            None => with_cancel(ctx),
+        }
+    }
+}
+
+impl Context {
+    /// Returns a builder deriving a context from the given parent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use ctx::{Context, background};
+    ///
+    /// let (ctx, _cancel) = Context::builder(background())
+    ///     .value(42)
+    ///     .value("user")
+    ///     .timeout(Duration::from_secs(1))
+    ///     .build();
+    /// assert_eq!(ctx.value(), Some(42));
+    /// assert_eq!(ctx.value(), Some("user"));
+    /// assert!(ctx.deadline().is_some());
+    /// ```
+    pub fn builder(parent: Context) -> ContextBuilder {
+        ContextBuilder {
+            parent,
+            values: Vec::new(),
+            timeout: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use futures::Future;
+    use {background, with_value, Context, ContextError};
+
+    #[test]
+    fn builder_test() {
+        let parent = with_value(background(), 1.0);
+        let (ctx, _) = Context::builder(parent)
+            .value(1)
+            .value("user")
+            .value(2)
+            .timeout(Duration::from_secs(1))
+            .build();
+
+        assert_eq!(ctx.value(), Some(1.0));
+        assert_eq!(ctx.value(), Some("user"));
+        assert_eq!(ctx.value(), Some(2));
+        assert!(ctx.deadline_is_about(Duration::from_secs(1), Duration::from_millis(50)));
+    }
+
+    #[test]
+    fn builder_cancel_test() {
+        let (ctx, cancel) = Context::builder(background()).value(42).build();
+        assert_eq!(ctx.deadline(), None);
+        cancel();
+        assert_eq!(ctx.wait(), Err(ContextError::Canceled));
+    }
+}
@@ -0,0 +1,103 @@
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use {Context, InnerContext, ContextError, ErrorDetail};
+use lock::lock;
+use futures::{Future, Poll};
+
+pub struct WithValueCache {
+    parent: Context,
+    cache: ValueCache,
+}
+
+/// The context nodes holding the resolved values (or the absence thereof) per value type, kept by
+/// a context created via `Context::cached`.
+#[derive(Default)]
+pub struct ValueCache(Mutex<HashMap<TypeId, Option<Context>>>);
+
+impl ValueCache {
+    /// Returns the cached node holding the value of the given type, resolving it using `f` on
+    /// the first call.
+    pub(crate) fn get_or_resolve<F>(&self, type_id: TypeId, f: F) -> Option<Context>
+    where
+        F: FnOnce() -> Option<Context>,
+    {
+        if let Some(entry) = lock(&self.0).get(&type_id) {
+            return entry.clone();
+        }
+
+        let node = f();
+        lock(&self.0).insert(type_id, node.clone());
+        node
+    }
+}
+
+impl InnerContext for WithValueCache {
+    fn value_cache(&self) -> Option<&ValueCache> {
+        Some(&self.cache)
+    }
+
+    fn parent(&self) -> Option<&Context> {
+        Some(&self.parent)
+    }
+
+    fn kind(&self) -> &'static str {
+        "cached"
+    }
+
+    fn poll_detail(&mut self) -> Poll<(), ErrorDetail> {
+        self.parent.poll_detail()
+    }
+}
+
+impl Future for WithValueCache {
+    type Item = ();
+    type Error = ContextError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.parent.poll_inner()
+    }
+}
+
+impl Context {
+    /// Wraps the context so that `value` lookups on the returned context are memoized per value
+    /// type: the chain is only walked on the first lookup of a type, subsequent lookups return a
+    /// clone of the cached result. Since contexts are immutable, the cache never needs to be
+    /// invalidated.
+    ///
+    /// Lookups on contexts derived from the returned one are not cached.
+    pub fn cached(self) -> Context {
+        Context::new(WithValueCache {
+            parent: self,
+            cache: ValueCache::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::any::TypeId;
+    use cache::ValueCache;
+    use {background, with_cancel, with_value};
+
+    #[test]
+    fn cached_test() {
+        let ctx = with_value(background(), 42).instrument_values();
+        let mut ctx = with_value(ctx, 1.0);
+        for _ in 0..20 {
+            ctx = with_cancel(ctx).0;
+        }
+        let ctx = ctx.cached();
+
+        for _ in 0..3 {
+            assert_eq!(ctx.value(), Some(42));
+            assert_eq!(ctx.value::<String>(), None);
+        }
+        assert_eq!(ctx.value(), Some(1.0));
+        assert!(ctx.value_ref::<ValueCache>().is_none());
+
+        let counts = ctx.value_access_counts();
+        assert_eq!(counts.get(&TypeId::of::<i32>()), Some(&1));
+        assert_eq!(counts.get(&TypeId::of::<String>()), Some(&1));
+    }
+}
@@ -0,0 +1,83 @@
+use {Context, ContextError, Done};
+use futures::{Async, Future, Poll, Stream};
+
+/// A stream forwarding the items of another stream until its context is done. Created via
+/// `Context::cancel_stream`.
+pub struct CancelStream<S> {
+    stream: S,
+    done: Done,
+    finished: bool,
+}
+
+impl Context {
+    /// Wraps the given stream so that it stops yielding items once the context is canceled or its
+    /// deadline is exceeded. In that case, the stream fails with the context's error and
+    /// terminates afterwards.
+    pub fn cancel_stream<S>(&self, stream: S) -> CancelStream<S>
+    where
+        S: Stream,
+        S::Error: Into<ContextError>,
+    {
+        CancelStream {
+            stream,
+            done: self.done(),
+            finished: false,
+        }
+    }
+}
+
+impl<S> Stream for CancelStream<S>
+where
+    S: Stream,
+    S::Error: Into<ContextError>,
+{
+    type Item = S::Item;
+    type Error = ContextError;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if self.finished {
+            return Ok(Async::Ready(None));
+        }
+        if let Err(err) = self.done.poll() {
+            self.finished = true;
+            return Err(err);
+        }
+        self.stream.poll().map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::{stream, Future, Stream};
+    use {background, with_cancel, ContextError};
+
+    #[test]
+    fn cancel_stream_test() {
+        let (ctx, cancel) = with_cancel(background());
+
+        let mut items = Vec::new();
+        let result = ctx
+            .cancel_stream(stream::iter_ok::<_, ContextError>(0..))
+            .for_each(|item| {
+                items.push(item);
+                if item == 2 {
+                    cancel();
+                }
+                Ok(())
+            })
+            .wait();
+
+        assert_eq!(result.unwrap_err(), ContextError::Canceled);
+        assert_eq!(items, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn forward_test() {
+        let ctx = background();
+        let items = ctx
+            .cancel_stream(stream::iter_ok::<_, ContextError>(0..3))
+            .collect()
+            .wait();
+        assert_eq!(items.unwrap(), vec![0, 1, 2]);
+    }
+}
@@ -0,0 +1,197 @@
+use std::sync::{Arc, Weak};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use {Context, ContextError, ErrorDetail, InnerContext, Signal};
+use futures::{Async, Future, Poll};
+use timer::schedule;
+
+/// How often the owner is checked while tasks are waiting for the context.
+const WATCH_INTERVAL: Duration = Duration::from_millis(10);
+
+pub struct WithCancelWhile<T> {
+    parent: Context,
+    signal: Signal,
+    owner: Weak<T>,
+    /// Whether the owner is already being watched on the scheduler thread.
+    watching: AtomicBool,
+    /// Set once the node is dropped, which stops watching the owner.
+    dropped: Arc<AtomicBool>,
+}
+
+impl<T: Send + Sync + 'static> WithCancelWhile<T> {
+    /// Registers the current task to be notified once the owner has been dropped. Since that
+    /// cannot be observed directly, the owner is watched on the scheduler thread.
+    fn register(&self) -> Result<(), ErrorDetail> {
+        self.signal.register();
+        if !self.watching.swap(true, Ordering::SeqCst) {
+            watch(self.owner.clone(), self.signal.clone(), self.dropped.clone());
+        }
+        self.check_owner()
+    }
+
+    /// Fires the signal once the owner has been dropped.
+    fn check_owner(&self) -> Result<(), ErrorDetail> {
+        if self.owner.upgrade().is_none() {
+            self.signal.fire();
+        }
+        if self.signal.is_fired() {
+            return Err(ErrorDetail {
+                error: ContextError::Canceled,
+                source_kind: "with_cancel_while",
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<T: Send + Sync + 'static> InnerContext for WithCancelWhile<T> {
+    fn parent(&self) -> Option<&Context> {
+        Some(&self.parent)
+    }
+
+    fn signal(&self) -> Option<&Signal> {
+        Some(&self.signal)
+    }
+
+    fn kind(&self) -> &'static str {
+        "with_cancel_while"
+    }
+
+    fn poll_detail(&mut self) -> Poll<(), ErrorDetail> {
+        self.check_owner()?;
+        let result = self.parent.poll_detail()?;
+        if result == Async::NotReady {
+            self.register()?;
+        }
+        Ok(result)
+    }
+
+    fn poll_detail_ref(&self) -> Poll<(), ErrorDetail> {
+        self.check_owner()?;
+        let result = self.parent.0.poll_detail_ref()?;
+        if result == Async::NotReady {
+            self.register()?;
+        }
+        Ok(result)
+    }
+}
+
+impl<T> Drop for WithCancelWhile<T> {
+    fn drop(&mut self) {
+        self.dropped.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Fires the signal once the owner has been dropped, checking it every `WATCH_INTERVAL` until
+/// then (or until the node has been dropped).
+fn watch<T>(owner: Weak<T>, signal: Signal, dropped: Arc<AtomicBool>)
+where
+    T: Send + Sync + 'static,
+{
+    schedule(Instant::now() + WATCH_INTERVAL, move || {
+        if dropped.load(Ordering::SeqCst) || signal.is_fired() {
+            return;
+        }
+        if owner.upgrade().is_none() {
+            signal.fire();
+        } else {
+            watch(owner, signal, dropped);
+        }
+    });
+}
+
+impl<T: Send + Sync + 'static> Future for WithCancelWhile<T> {
+    type Item = ();
+    type Error = ContextError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.poll_detail().map_err(|detail| detail.error)
+    }
+}
+
+/// Returns a copy of the parent context, which is canceled once all strong references to the
+/// given owner have been dropped, i.e. to tie the context to the lifetime of some resource.
+///
+/// Only a `Weak` reference to the owner is kept, which is upgraded whenever the context is
+/// polled. Since dropping the owner does not notify anyone, the owner is additionally checked
+/// every 10ms on the timer's shared scheduler thread once a task is waiting for the context, so
+/// that waiting tasks are notified shortly after the owner has been dropped.
+///
+/// # Example
+///
+/// ```
+/// extern crate ctx;
+/// extern crate futures;
+///
+/// use std::sync::Arc;
+/// use ctx::{with_cancel_while, background, ContextError};
+/// use futures::Future;
+///
+/// fn main() {
+///     let owner = Arc::new(());
+///     let ctx = with_cancel_while(background(), &owner);
+///     drop(owner);
+///
+///     assert_eq!(ctx.wait().unwrap_err(), ContextError::Canceled);
+/// }
+/// ```
+pub fn with_cancel_while<T>(parent: Context, owner: &Arc<T>) -> Context
+where
+    T: Send + Sync + 'static,
+{
+    Context::new(WithCancelWhile {
+        parent,
+        signal: Signal::new(),
+        owner: Arc::downgrade(owner),
+        watching: AtomicBool::new(false),
+        dropped: Arc::new(AtomicBool::new(false)),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+    use futures::{future, Async, Future};
+    use cancel_while::with_cancel_while;
+    use {background, with_cancel, ContextError};
+
+    #[test]
+    fn owner_dropped_test() {
+        let owner = Arc::new(String::from("connection"));
+        let mut ctx = with_cancel_while(background(), &owner);
+
+        let result = future::poll_fn(|| Ok::<_, ()>(Async::Ready(ctx.poll()))).wait();
+        assert_eq!(result, Ok(Ok(Async::NotReady)));
+        assert_eq!(ctx.terminal_reason(), None);
+
+        drop(owner);
+        let result = future::poll_fn(|| Ok::<_, ()>(Async::Ready(ctx.poll()))).wait();
+        assert_eq!(result, Ok(Err(ContextError::Canceled)));
+        assert_eq!(ctx.terminal_reason(), Some(ContextError::Canceled));
+    }
+
+    #[test]
+    fn parent_canceled_test() {
+        let owner = Arc::new(());
+        let (parent, cancel) = with_cancel(background());
+        let ctx = with_cancel_while(parent, &owner);
+        cancel();
+
+        assert_eq!(ctx.wait().unwrap_err(), ContextError::Canceled);
+    }
+
+    #[test]
+    fn wait_test() {
+        let owner = Arc::new(());
+        let ctx = with_cancel_while(background(), &owner);
+        let dropper = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            drop(owner);
+        });
+
+        assert_eq!(ctx.wait().unwrap_err(), ContextError::Canceled);
+        dropper.join().unwrap();
+    }
+}
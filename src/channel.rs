@@ -0,0 +1,160 @@
+use {Context, ContextError, Done};
+use futures::{Async, AsyncSink, Future, Poll, Sink, StartSend, Stream};
+use futures::sync::mpsc;
+
+/// The sending half of a channel created via `Context::bounded`.
+pub struct Sender<T> {
+    inner: mpsc::Sender<T>,
+    done: Done,
+}
+
+/// The receiving half of a channel created via `Context::bounded`.
+pub struct Receiver<T> {
+    inner: mpsc::Receiver<T>,
+    done: Done,
+}
+
+/// The error returned when sending on a channel that has been closed, either because its context
+/// is done or because the receiver has been dropped. Contains the value that failed to be sent,
+/// or `None` if the value had already been queued when the context was done (in which case it is
+/// discarded together with the channel).
+#[derive(Debug, PartialEq)]
+pub struct SendError<T>(pub Option<T>);
+
+impl Context {
+    /// Creates a bounded channel whose lifetime is tied to the context. Once the context is
+    /// canceled or its deadline is exceeded, sending fails and the receiver resolves with the
+    /// context's error.
+    pub fn bounded<T>(&self, cap: usize) -> (Sender<T>, Receiver<T>) {
+        let (tx, rx) = mpsc::channel(cap);
+        (
+            Sender {
+                inner: tx,
+                done: self.done(),
+            },
+            Receiver {
+                inner: rx,
+                done: self.done(),
+            },
+        )
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        Sender {
+            inner: self.inner.clone(),
+            done: self.done.clone(),
+        }
+    }
+}
+
+impl<T> Sink for Sender<T> {
+    type SinkItem = T;
+    type SinkError = SendError<T>;
+
+    fn start_send(&mut self, item: T) -> StartSend<T, SendError<T>> {
+        if self.done.check().is_some() {
+            return Err(SendError(Some(item)));
+        }
+        match self.inner.start_send(item) {
+            // also wait for the context, so that a sender parked on a full channel is woken once
+            // the context is done
+            Ok(AsyncSink::NotReady(item)) => match self.done.poll() {
+                Err(_) => Err(SendError(Some(item))),
+                Ok(_) => Ok(AsyncSink::NotReady(item)),
+            },
+            Ok(AsyncSink::Ready) => Ok(AsyncSink::Ready),
+            Err(err) => Err(SendError(Some(err.into_inner()))),
+        }
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), SendError<T>> {
+        match self.inner.poll_complete() {
+            Ok(Async::NotReady) => match self.done.poll() {
+                Err(_) => Err(SendError(None)),
+                Ok(_) => Ok(Async::NotReady),
+            },
+            Ok(Async::Ready(())) => Ok(Async::Ready(())),
+            Err(err) => Err(SendError(Some(err.into_inner()))),
+        }
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+    type Error = ContextError;
+
+    fn poll(&mut self) -> Poll<Option<T>, ContextError> {
+        if let Err(err) = self.done.poll() {
+            self.inner.close();
+            return Err(err);
+        }
+        match self.inner.poll() {
+            Ok(ready) => Ok(ready),
+            Err(()) => Ok(Async::Ready(None)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+    use std::time::Duration;
+    use futures::{AsyncSink, Future, Sink, Stream};
+    use futures::future::lazy;
+    use channel::SendError;
+    use {background, with_cancel, ContextError};
+
+    #[test]
+    fn send_test() {
+        let (ctx, _cancel) = with_cancel(background());
+        let (tx, rx) = ctx.bounded(1);
+
+        let tx = tx.send(1).wait().unwrap();
+        drop(tx);
+        assert_eq!(rx.collect().wait().unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn cancel_test() {
+        let (ctx, cancel) = with_cancel(background());
+        let (tx, rx) = ctx.bounded(1);
+        cancel();
+
+        assert_eq!(tx.send(1).wait().err(), Some(SendError(Some(1))));
+        assert_eq!(rx.collect().wait().unwrap_err(), ContextError::Canceled);
+    }
+
+    #[test]
+    fn cancel_blocked_send_test() {
+        let (ctx, cancel) = with_cancel(background());
+        let (tx, _rx) = ctx.bounded(1);
+
+        // fill the channel, including the sender's own slot
+        let mut tx = tx.send(1).wait().unwrap();
+        let queued = lazy(|| tx.start_send(2)).wait();
+        assert_eq!(queued, Ok(AsyncSink::Ready));
+
+        let canceler = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            cancel();
+        });
+        assert_eq!(tx.send(3).wait().err(), Some(SendError(Some(3))));
+        canceler.join().unwrap();
+    }
+
+    #[test]
+    fn cancel_pending_send_test() {
+        let (ctx, cancel) = with_cancel(background());
+        let (tx, _rx) = ctx.bounded(0);
+
+        // the value is queued, but the sender is parked until the receiver catches up
+        let canceler = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            cancel();
+        });
+        assert_eq!(tx.send(1).wait().err(), Some(SendError(None)));
+        canceler.join().unwrap();
+    }
+}
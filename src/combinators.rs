@@ -0,0 +1,463 @@
+use std::time::Duration;
+use {background, with_cancel, with_timeout, Context, ContextError};
+use futures::Future;
+use futures::future::{self, Either};
+
+/// Wraps the given handler so that each call runs it with a fresh context bounded by `timeout`.
+/// The handler receives the context, and the returned future resolves with
+/// `ContextError::DeadlineExceeded` if the handler does not complete in time.
+///
+/// # Examples
+///
+/// ```
+/// extern crate ctx;
+/// extern crate futures;
+///
+/// use std::time::Duration;
+/// use ctx::with_handler_timeout;
+/// use futures::Future;
+/// use futures::future;
+///
+/// fn main() {
+///     let handler = with_handler_timeout(Duration::from_secs(1), |ctx| {
+///         future::ok(ctx.deadline().is_some())
+///     });
+///     assert_eq!(handler().wait(), Ok(true));
+/// }
+/// ```
+pub fn with_handler_timeout<F, Fut>(
+    timeout: Duration,
+    f: F,
+) -> impl Fn() -> Box<dyn Future<Item = Fut::Item, Error = ContextError>>
+where
+    F: Fn(&Context) -> Fut,
+    Fut: Future<Error = ContextError> + 'static,
+{
+    move || {
+        let (ctx, _) = with_timeout(background(), timeout);
+        let fut = f(&ctx);
+        Box::new(ctx.complete_with(fut))
+    }
+}
+
+/// The outcome of racing futures against a context via `ctx_select!`.
+#[derive(Debug, PartialEq)]
+pub enum Selected<T> {
+    /// The first future to complete succeeded with the given item.
+    Ready(T),
+    /// The context has been canceled first.
+    Canceled,
+    /// The deadline of the context has been exceeded first.
+    DeadlineExceeded,
+}
+
+/// Races the given futures against the context and resolves with the outcome as a `Selected`.
+/// Errors other than cancelation and an exceeded deadline (including errors of the first future
+/// to complete) are returned as is.
+///
+/// All futures must be of the same type; box them to race futures of different types.
+///
+/// # Panics
+///
+/// Panics if no future is given.
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate ctx;
+/// extern crate futures;
+///
+/// use std::time::Duration;
+/// use ctx::{with_timeout, background, ContextError, Selected};
+/// use futures::Future;
+/// use futures::future;
+///
+/// fn main() {
+///     let (ctx, _) = with_timeout(background(), Duration::from_millis(50));
+///     match ctx_select!(ctx, future::empty::<i32, ContextError>()).wait() {
+///         Ok(Selected::Ready(item)) => println!("ready: {}", item),
+///         Ok(Selected::Canceled) => println!("canceled"),
+///         Ok(Selected::DeadlineExceeded) => println!("timed out"),
+///         Err(err) => println!("failed: {}", err),
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! ctx_select {
+    ($ctx:expr, $($fut:expr),+ $(,)*) => {
+        $crate::select_futures($ctx, vec![$($fut),+])
+    };
+}
+
+#[doc(hidden)]
+pub fn select_futures<F, I>(
+    ctx: Context,
+    futures: I,
+) -> impl Future<Item = Selected<F::Item>, Error = ContextError>
+where
+    I: IntoIterator<Item = F>,
+    F: Future<Error = ContextError>,
+{
+    future::select_all(futures)
+        .select2(ctx)
+        .then(|result| match result {
+            Ok(Either::A(((item, _, _), _))) => Ok(Selected::Ready(item)),
+            Ok(Either::B(_)) | Err(Either::B((ContextError::Canceled, _))) => {
+                Ok(Selected::Canceled)
+            }
+            Err(Either::B((ContextError::DeadlineExceeded, _))) => Ok(Selected::DeadlineExceeded),
+            Err(Either::A(((err, _, _), _))) | Err(Either::B((err, _))) => Err(err),
+        })
+}
+
+/// Resolves to `default` if the given future fails with `ContextError::Canceled` or
+/// `ContextError::DeadlineExceeded`, which is useful for best-effort operations. Other errors are
+/// passed through.
+///
+/// # Examples
+///
+/// ```
+/// extern crate ctx;
+/// extern crate futures;
+///
+/// use ctx::{or_default, with_cancel, background};
+/// use futures::Future;
+///
+/// fn main() {
+///     let (ctx, cancel) = with_cancel(background());
+///     cancel();
+///     let recommendations = ctx.map(|_| vec!["fallback"]);
+///     assert_eq!(or_default(recommendations, Vec::new()).wait(), Ok(Vec::new()));
+/// }
+/// ```
+pub fn or_default<F>(f: F, default: F::Item) -> impl Future<Item = F::Item, Error = F::Error>
+where
+    F: Future<Error = ContextError>,
+{
+    f.or_else(|err| match err {
+        ContextError::Canceled | ContextError::DeadlineExceeded => Ok(default),
+        err => Err(err),
+    })
+}
+
+impl Context {
+    /// Runs the given future bounded by the context. Resolves with the future's item if it
+    /// completes first, or with the context's error otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate ctx;
+    /// extern crate futures;
+    ///
+    /// use ctx::{with_cancel, background, ContextError};
+    /// use futures::Future;
+    /// use futures::future;
+    ///
+    /// fn main() {
+    ///     let (ctx, cancel) = with_cancel(background());
+    ///     cancel();
+    ///     let result = ctx.complete_with(future::empty::<(), _>()).wait();
+    ///     assert_eq!(result, Err(ContextError::Canceled));
+    /// }
+    /// ```
+    pub fn complete_with<F>(self, f: F) -> impl Future<Item = F::Item, Error = ContextError>
+    where
+        F: Future<Error = ContextError>,
+    {
+        f.select2(self).then(|result| match result {
+            Ok(Either::A((item, _))) => Ok(item),
+            Ok(Either::B(_)) => Err(ContextError::Canceled),
+            Err(Either::A((err, _))) | Err(Either::B((err, _))) => Err(err),
+        })
+    }
+
+    /// Runs the given futures concurrently bounded by the context and resolves with both of their
+    /// items. If either future fails, or if the context resolves before both futures completed,
+    /// both futures are dropped and the error is returned.
+    pub fn join2<A, B>(
+        self,
+        a: A,
+        b: B,
+    ) -> impl Future<Item = (A::Item, B::Item), Error = ContextError>
+    where
+        A: Future<Error = ContextError>,
+        B: Future<Error = ContextError>,
+    {
+        self.complete_with(a.join(b))
+    }
+
+    /// Runs the given attempts concurrently and resolves with the first successful one, bounded
+    /// by the context. Each attempt is created with a shared cancelable child context, which is
+    /// canceled once an attempt succeeds (or the hedge resolves otherwise), so that the other
+    /// attempts (and everything they derived from it) stop. If all attempts fail, the error of the
+    /// last failing attempt is returned; if the context resolves first, its error is returned.
+    ///
+    /// This implements speculative execution (hedged requests) to reduce tail latency.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `attempts` is empty.
+    pub fn hedge<F, C, I>(self, attempts: I) -> impl Future<Item = F::Item, Error = ContextError>
+    where
+        I: IntoIterator<Item = C>,
+        C: FnOnce(Context) -> F,
+        F: Future<Error = ContextError>,
+    {
+        let (ctx, cancel) = with_cancel(self);
+        let attempts: Vec<F> = attempts.into_iter().map(|attempt| attempt(ctx.clone())).collect();
+        let first = future::select_ok(attempts).map(|(item, _losers)| item);
+        ctx.complete_with(first).then(move |result| {
+            cancel();
+            result
+        })
+    }
+
+    /// Runs the given children concurrently bounded by the context and resolves with all of their
+    /// items. Once a child fails, its siblings are canceled by dropping them, and the error of the
+    /// failing child is returned together with the error the siblings have been stopped with
+    /// (`ContextError::Canceled`). If the context resolves first, all children are dropped and the
+    /// context's error is returned (converted into the children's error type as well).
+    ///
+    /// This implements fail-fast supervision of child tasks.
+    pub fn supervise<F, I>(
+        self,
+        children: I,
+    ) -> impl Future<Item = Vec<F::Item>, Error = (F::Error, ContextError)>
+    where
+        I: IntoIterator<Item = F>,
+        F: Future,
+        F::Error: From<ContextError>,
+    {
+        future::join_all(children)
+            .select2(self)
+            .then(|result| match result {
+                Ok(Either::A((items, _))) => Ok(items),
+                Ok(Either::B(_)) => Err((ContextError::Canceled.into(), ContextError::Canceled)),
+                Err(Either::A((err, _))) => Err((err, ContextError::Canceled)),
+                Err(Either::B((err, _))) => Err((err.into(), err)),
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+    use std::time::Duration;
+    use futures::{Async, Future, Poll};
+    use futures::future;
+    use timer::timer;
+    use combinators::{or_default, with_handler_timeout, Selected};
+    use {background, with_cancel, with_timeout, Context, ContextError};
+
+    /// Wraps a future and records whether it has been dropped before completing.
+    struct Attempt<F> {
+        inner: F,
+        canceled: Arc<AtomicBool>,
+        completed: bool,
+    }
+
+    impl<F: Future> Future for Attempt<F> {
+        type Item = F::Item;
+        type Error = F::Error;
+
+        fn poll(&mut self) -> Poll<F::Item, F::Error> {
+            let result = self.inner.poll();
+            self.completed = !matches!(result, Ok(Async::NotReady));
+            result
+        }
+    }
+
+    impl<F> Drop for Attempt<F> {
+        fn drop(&mut self) {
+            if !self.completed {
+                self.canceled.store(true, Ordering::SeqCst);
+            }
+        }
+    }
+
+    fn attempt(
+        delay: Duration,
+        val: i32,
+        canceled: Arc<AtomicBool>,
+    ) -> Box<dyn Future<Item = i32, Error = ContextError>> {
+        Box::new(Attempt {
+            inner: timer()
+                .sleep(delay)
+                .map(move |_| val)
+                .map_err(|_| ContextError::DeadlineTooLong),
+            canceled,
+            completed: false,
+        })
+    }
+
+    type Hedged = Box<dyn FnOnce(Context) -> Box<dyn Future<Item = i32, Error = ContextError>>>;
+
+    #[test]
+    fn hedge_test() {
+        let fast_canceled = Arc::new(AtomicBool::new(false));
+        let slow_canceled = Arc::new(AtomicBool::new(false));
+        let (ctx, _cancel) = with_cancel(background());
+        let (tx, rx) = ::std::sync::mpsc::channel();
+
+        let slow = {
+            let canceled = slow_canceled.clone();
+            move |ctx: Context| {
+                // observe the shared context outside of the hedged futures
+                thread::spawn(move || tx.send(ctx.wait()).unwrap());
+                attempt(Duration::from_secs(2), 1, canceled)
+            }
+        };
+        let fast = {
+            let canceled = fast_canceled.clone();
+            move |_| attempt(Duration::from_millis(50), 2, canceled)
+        };
+        let result = ctx
+            .hedge(vec![Box::new(slow) as Hedged, Box::new(fast)])
+            .wait();
+
+        assert_eq!(result, Ok(2));
+        assert!(slow_canceled.load(Ordering::SeqCst));
+        assert!(!fast_canceled.load(Ordering::SeqCst));
+        let observed = rx.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert_eq!(observed, Err(ContextError::Canceled));
+    }
+
+    #[test]
+    fn hedge_timeout_test() {
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(50));
+        let result = ctx
+            .hedge(vec![|_| future::empty::<i32, ContextError>()])
+            .wait();
+        assert_eq!(result, Err(ContextError::DeadlineExceeded));
+    }
+
+    #[test]
+    fn hedge_all_failed_test() {
+        let (ctx, _cancel) = with_cancel(background());
+        let result = ctx
+            .hedge(vec![|_| future::err::<i32, _>(ContextError::WorkExhausted)])
+            .wait();
+        assert_eq!(result, Err(ContextError::WorkExhausted));
+    }
+
+    #[test]
+    fn supervise_test() {
+        let (ctx, _cancel) = with_cancel(background());
+        let result = ctx
+            .supervise((1..3).map(future::ok::<i32, ContextError>))
+            .wait();
+        assert_eq!(result, Ok(vec![1, 2]));
+    }
+
+    #[test]
+    fn supervise_error_test() {
+        let sibling_canceled = Arc::new(AtomicBool::new(false));
+        let (ctx, _cancel) = with_cancel(background());
+        let failing = timer()
+            .sleep(Duration::from_millis(50))
+            .then(|_| Err(ContextError::WorkExhausted));
+        let result = ctx
+            .supervise(vec![
+                attempt(Duration::from_secs(2), 1, sibling_canceled.clone()),
+                Box::new(failing),
+            ])
+            .wait();
+
+        assert_eq!(result, Err((ContextError::WorkExhausted, ContextError::Canceled)));
+        assert!(sibling_canceled.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn supervise_timeout_test() {
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(50));
+        let result = ctx
+            .supervise(vec![future::empty::<i32, ContextError>()])
+            .wait();
+        let exceeded = ContextError::DeadlineExceeded;
+        assert_eq!(result, Err((exceeded, exceeded)));
+    }
+
+    #[test]
+    fn handler_timeout_test() {
+        let handler = with_handler_timeout(Duration::from_millis(50), |_| {
+            timer()
+                .sleep(Duration::from_secs(2))
+                .map_err(|_| ContextError::DeadlineTooLong)
+        });
+        assert_eq!(handler().wait(), Err(ContextError::DeadlineExceeded));
+    }
+
+    #[test]
+    fn or_default_test() {
+        assert_eq!(or_default(future::ok(1), 0).wait(), Ok(1));
+
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(50));
+        assert_eq!(or_default(ctx.map(|_| 1), 0).wait(), Ok(0));
+
+        let (ctx, cancel) = with_cancel(background());
+        cancel();
+        assert_eq!(or_default(ctx.map(|_| 1), 0).wait(), Ok(0));
+
+        let failed = future::err::<i32, _>(ContextError::WorkExhausted);
+        assert_eq!(or_default(failed, 0).wait(), Err(ContextError::WorkExhausted));
+    }
+
+    #[test]
+    fn ctx_select_test() {
+        let (ctx, _cancel) = with_cancel(background());
+        let result = ctx_select!(ctx, future::ok(1), future::ok(2)).wait();
+        assert_eq!(result, Ok(Selected::Ready(1)));
+
+        let (ctx, cancel) = with_cancel(background());
+        cancel();
+        let result = ctx_select!(ctx, future::empty::<i32, _>()).wait();
+        assert_eq!(result, Ok(Selected::Canceled));
+
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(50));
+        let result = ctx_select!(ctx, future::empty::<i32, _>()).wait();
+        assert_eq!(result, Ok(Selected::DeadlineExceeded));
+
+        let (ctx, _cancel) = with_cancel(background());
+        let result = ctx_select!(ctx, future::err::<i32, _>(ContextError::WorkExhausted)).wait();
+        assert_eq!(result, Err(ContextError::WorkExhausted));
+    }
+
+    #[test]
+    fn complete_with_test() {
+        let (ctx, _cancel) = with_cancel(background());
+        assert_eq!(ctx.complete_with(future::ok(1)).wait(), Ok(1));
+
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(50));
+        let result = ctx.complete_with(future::empty::<i32, _>()).wait();
+        assert_eq!(result, Err(ContextError::DeadlineExceeded));
+    }
+
+    #[test]
+    fn join2_test() {
+        let (ctx, _cancel) = with_cancel(background());
+        assert_eq!(ctx.join2(future::ok(1), future::ok("a")).wait(), Ok((1, "a")));
+
+        let a_canceled = Arc::new(AtomicBool::new(false));
+        let b_canceled = Arc::new(AtomicBool::new(false));
+        let (ctx, cancel) = with_cancel(background());
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            cancel();
+        });
+        let result = ctx
+            .join2(
+                attempt(Duration::from_millis(0), 1, a_canceled.clone()),
+                attempt(Duration::from_secs(2), 2, b_canceled.clone()),
+            )
+            .wait();
+
+        assert_eq!(result, Err(ContextError::Canceled));
+        assert!(b_canceled.load(Ordering::SeqCst));
+        // the first future completed before the context has been canceled
+        assert!(!a_canceled.load(Ordering::SeqCst));
+    }
+}
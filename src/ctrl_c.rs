@@ -0,0 +1,79 @@
+use std::mem;
+use std::sync::{Mutex, OnceLock};
+use {background, Context, Signal, WeakContext, WithCancel};
+use lock::lock;
+
+/// The contexts waiting for Ctrl-C. Only weak handles are kept, so that contexts that have been
+/// dropped do not accumulate.
+static PENDING: OnceLock<Mutex<Vec<WeakContext>>> = OnceLock::new();
+
+/// Returns a top-level context, which is canceled once the user presses Ctrl-C, e.g. as the root
+/// context of a CLI tool.
+///
+/// This installs a process-global Ctrl-C handler via the `ctrlc` crate on the first call, which
+/// is shared by all contexts returned by subsequent calls. A single Ctrl-C cancels all contexts
+/// returned so far. Since the handler replaces the default behavior, the process is no longer
+/// terminated by Ctrl-C; it is up to the application to exit once the context is canceled.
+///
+/// Requires the `ctrlc` feature.
+///
+/// # Panics
+///
+/// Panics if the handler cannot be installed, e.g. because another Ctrl-C handler has already
+/// been installed via the `ctrlc` crate.
+pub fn cancel_on_ctrlc() -> Context {
+    let pending = PENDING.get_or_init(|| {
+        ctrlc::set_handler(on_ctrlc).expect("failed to install the Ctrl-C handler");
+        Mutex::new(Vec::new())
+    });
+    let ctx = Context::new(WithCancel::new(background(), Signal::new(), false));
+    let mut pending = lock(pending);
+    pending.retain(|ctx| ctx.upgrade().is_some());
+    pending.push(ctx.downgrade());
+    ctx
+}
+
+/// Cancels all contexts returned by `cancel_on_ctrlc` so far.
+fn on_ctrlc() {
+    if let Some(pending) = PENDING.get() {
+        for ctx in mem::take(&mut *lock(pending)).iter().filter_map(WeakContext::upgrade) {
+            if let Some(signal) = ctx.0.signal() {
+                signal.fire();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::Future;
+    use ctrl_c::{cancel_on_ctrlc, on_ctrlc, PENDING};
+    use lock::lock;
+    use ContextError;
+
+    #[test]
+    fn cancel_on_ctrlc_test() {
+        let first = cancel_on_ctrlc();
+        let second = cancel_on_ctrlc();
+        assert_eq!(first.terminal_reason(), None);
+
+        on_ctrlc();
+        assert_eq!(first.wait(), Err(ContextError::Canceled));
+        assert_eq!(second.wait(), Err(ContextError::Canceled));
+
+        // contexts created afterwards wait for the next Ctrl-C
+        let third = cancel_on_ctrlc();
+        assert_eq!(third.terminal_reason(), None);
+    }
+
+    #[test]
+    fn dropped_contexts_test() {
+        let ctx = cancel_on_ctrlc();
+        for _ in 0..100 {
+            drop(cancel_on_ctrlc());
+        }
+        // the contexts of `cancel_on_ctrlc_test` might be pending concurrently
+        assert!(lock(PENDING.get().unwrap()).len() <= 5);
+        drop(ctx);
+    }
+}
@@ -0,0 +1,193 @@
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+use {Context, ContextError, Signal};
+use futures::{Future, Poll, Async};
+use futures::future::Shared;
+use futures::task::AtomicTask;
+use tokio_timer::Sleep;
+use lock::lock;
+use timer::timer;
+
+/// A future that resolves once the context it has been created from (see `Context::done`) is
+/// canceled or its deadline is exceeded. Contrary to the context itself, it does not require
+/// ownership of the context.
+///
+/// Only cancelation of context nodes exposing a `Signal` (see `InnerContext::signal`) is
+/// observed.
+pub struct Done {
+    signals: Vec<Signal>,
+    when: Option<Instant>,
+    /// The task that polled last, which is registered with the signals on the first poll. Only a
+    /// weak reference is kept by the signals, so that the registration ends with the future.
+    task: Arc<AtomicTask>,
+    registered: AtomicBool,
+    sleep: Option<Mutex<Sleep>>,
+}
+
+impl Done {
+    pub(crate) fn new(ctx: &Context) -> Self {
+        let signals = ctx.signals().map(|(_, signal)| signal.clone()).collect();
+
+        Done::from_parts(signals, ctx.deadline())
+    }
+
+    pub(crate) fn from_parts(signals: Vec<Signal>, when: Option<Instant>) -> Self {
+        let sleep = when.map(|when| {
+            let timeout = when.saturating_duration_since(Instant::now());
+            Mutex::new(timer().sleep(timeout))
+        });
+        Done {
+            signals,
+            when,
+            task: Arc::new(AtomicTask::new()),
+            registered: AtomicBool::new(false),
+            sleep,
+        }
+    }
+
+    /// Returns the error the context resolved with, if it is already done, without registering
+    /// the current task for notifications.
+    pub(crate) fn check(&self) -> Option<ContextError> {
+        if let Some(err) = self.signals.iter().find_map(Signal::error) {
+            Some(err)
+        } else if self.when.is_some_and(|when| when <= Instant::now()) {
+            Some(ContextError::DeadlineExceeded)
+        } else {
+            None
+        }
+    }
+
+    /// Same as `poll`, but through a shared reference. Only the task that polled last is notified.
+    pub(crate) fn poll_ref(&self) -> Poll<(), ContextError> {
+        self.task.register();
+        if !self.registered.swap(true, Ordering::SeqCst) {
+            for signal in &self.signals {
+                signal.add_waiter(&self.task);
+            }
+        }
+        if let Some(err) = self.signals.iter().find_map(Signal::error) {
+            return Err(err);
+        }
+
+        self.poll_deadline()
+    }
+
+    /// Polls the deadline portion of the future only, i.e. ignores cancelation.
+    pub(crate) fn poll_deadline(&self) -> Poll<(), ContextError> {
+        match self.sleep.as_ref().map(|sleep| lock(sleep).poll()) {
+            Some(Ok(Async::Ready(_))) => Err(ContextError::DeadlineExceeded),
+            Some(Err(_)) => Err(ContextError::DeadlineTooLong),
+            _ => Ok(Async::NotReady),
+        }
+    }
+}
+
+impl Clone for Done {
+    fn clone(&self) -> Self {
+        Done::from_parts(self.signals.clone(), self.when)
+    }
+}
+
+impl Future for Done {
+    type Item = ();
+    type Error = ContextError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.poll_ref()
+    }
+}
+
+/// A cloneable future that resolves once the context it has been created from (see
+/// `Context::shared_done`) is canceled or its deadline is exceeded. All clones share the same
+/// underlying `Done` future.
+pub type SharedDone = Shared<Done>;
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use std::thread;
+    use futures::{future, Async, Future};
+    use {background, with_cancel, with_timeout, with_value, ContextError};
+
+    #[test]
+    fn done_cancel_test() {
+        let (parent, cancel) = with_cancel(background());
+        let ctx = with_value(parent, 42);
+        let done = ctx.done();
+
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            cancel();
+        });
+
+        assert_eq!(done.wait().unwrap_err(), ContextError::Canceled);
+        assert_eq!(ctx.value(), Some(42));
+    }
+
+    #[test]
+    fn done_deadline_test() {
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(50));
+        assert_eq!(ctx.done().wait().unwrap_err(), ContextError::DeadlineExceeded);
+    }
+
+    #[test]
+    fn shared_done_clones_test() {
+        let (ctx, cancel) = with_cancel(background());
+        let handles: Vec<_> = (0..3)
+            .map(|_| {
+                let done = ctx.clone().shared_done();
+                thread::spawn(move || done.map(|_| ()).map_err(|err| *err).wait())
+            })
+            .collect();
+        thread::sleep(Duration::from_millis(50));
+        cancel();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), Err(ContextError::Canceled));
+        }
+        assert_eq!(*ctx.shared_done().wait().unwrap_err(), ContextError::Canceled);
+    }
+
+    #[test]
+    fn shared_done_test() {
+        let (ctx, cancel) = with_cancel(background());
+        let done = ctx.shared_done();
+
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let done = done.clone();
+                thread::spawn(move || done.map(|_| ()).map_err(|err| *err).wait())
+            })
+            .collect();
+        thread::sleep(Duration::from_millis(50));
+        cancel();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), Err(ContextError::Canceled));
+        }
+        assert_eq!(*done.wait().unwrap_err(), ContextError::Canceled);
+    }
+
+    #[test]
+    fn waiters_bounded_test() {
+        let (ctx, cancel) = with_cancel(background());
+        let signal = ctx.0.signal().unwrap().clone();
+
+        for _ in 0..100 {
+            let mut done = ctx.done();
+            future::poll_fn(|| Ok::<_, ()>(Async::Ready(done.poll()))).wait().unwrap().unwrap();
+        }
+        assert!(signal.waiter_count() <= 1);
+
+        let done = ctx.done();
+        for _ in 0..100 {
+            future::poll_fn(|| Ok::<_, ()>(Async::Ready(done.poll_ref()))).wait().unwrap().unwrap();
+        }
+        assert_eq!(signal.waiter_count(), 1);
+
+        cancel();
+        assert_eq!(done.wait().unwrap_err(), ContextError::Canceled);
+        assert_eq!(signal.waiter_count(), 0);
+    }
+}
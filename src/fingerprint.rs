@@ -0,0 +1,145 @@
+use std::any::{type_name, Any};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use {Context, InnerContext, ContextError, ErrorDetail};
+use futures::{Future, Poll};
+use with_value::check_value;
+
+pub struct WithHashableValue<V>
+where
+    V: Any + Hash + Send + Sync,
+{
+    parent: Context,
+    val: V,
+}
+
+impl<V> InnerContext for WithHashableValue<V>
+where
+    V: Any + Hash + Send + Sync,
+{
+    fn value(&self) -> Option<&dyn Any> {
+        Some(&self.val as &dyn Any)
+    }
+
+    fn hash_value(&self, mut state: &mut dyn Hasher) {
+        self.val.hash(&mut state);
+    }
+
+    fn value_type_name(&self) -> Option<&'static str> {
+        Some(type_name::<V>())
+    }
+
+    fn parent(&self) -> Option<&Context> {
+        Some(&self.parent)
+    }
+
+    fn kind(&self) -> &'static str {
+        "with_hashable_value"
+    }
+
+    fn poll_detail(&mut self) -> Poll<(), ErrorDetail> {
+        self.parent.poll_detail()
+    }
+}
+
+impl<V> Future for WithHashableValue<V>
+where
+    V: Any + Hash + Send + Sync,
+{
+    type Item = ();
+    type Error = ContextError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.parent.poll_inner()
+    }
+}
+
+/// Same as `with_value`, but the value itself (and not only its type) contributes to
+/// `Context::value_fingerprint`. The same checks as for `with_value` apply.
+///
+/// # Examples
+///
+/// ```
+/// use ctx::{with_hashable_value, background};
+///
+/// let a = with_hashable_value(background(), "tenant-a");
+/// let b = with_hashable_value(background(), "tenant-b");
+/// assert_eq!(a.value(), Some("tenant-a"));
+/// assert_ne!(a.value_fingerprint(), b.value_fingerprint());
+/// ```
+pub fn with_hashable_value<V>(parent: Context, val: V) -> Context
+where
+    V: Any + Hash + Send + Sync,
+{
+    check_value::<V>(&parent);
+    Context::new(WithHashableValue { parent, val })
+}
+
+impl Context {
+    /// Returns a hash of the values visible from this context, e.g. to be used as a cache key.
+    /// Values associated via `with_value` only contribute their type, whereas values associated
+    /// via `with_hashable_value` contribute their type and contents. Only the closest value per
+    /// type contributes, i.e. shadowed values and values hidden by `with_value_filter` are
+    /// ignored.
+    pub fn value_fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for (type_id, ctx) in self.visible_values() {
+            type_id.hash(&mut hasher);
+            ctx.0.hash_value(&mut hasher);
+        }
+        hasher.finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::any::TypeId;
+    use fingerprint::with_hashable_value;
+    use {background, with_cancel, with_value, with_value_filter, with_lazy_value};
+
+    #[derive(Hash)]
+    struct User(u32);
+
+    #[test]
+    fn same_values_test() {
+        let a = with_hashable_value(background(), User(1));
+        let a = with_value(a, 1.0);
+        let (b, _) = with_cancel(with_hashable_value(background(), User(1)));
+        let b = with_value(b, 2.0);
+
+        assert_eq!(a.value_fingerprint(), b.value_fingerprint());
+    }
+
+    #[test]
+    fn different_values_test() {
+        let a = with_hashable_value(background(), User(1));
+        let b = with_hashable_value(background(), User(2));
+        let c = with_value(background(), 1.0);
+
+        assert_ne!(a.value_fingerprint(), b.value_fingerprint());
+        assert_ne!(a.value_fingerprint(), c.value_fingerprint());
+        assert_ne!(a.value_fingerprint(), background().value_fingerprint());
+    }
+
+    #[test]
+    fn shadowed_values_test() {
+        let a = with_hashable_value(background(), User(1));
+        let a = with_hashable_value(a, User(2));
+        let b = with_hashable_value(background(), User(2));
+        assert_eq!(a.value_fingerprint(), b.value_fingerprint());
+    }
+
+    #[test]
+    fn filtered_and_lazy_values_test() {
+        let a = with_hashable_value(background(), User(1));
+        let a = with_value(a, 1.0);
+        let a = with_value_filter(a, &[TypeId::of::<User>()]);
+        let b = with_hashable_value(background(), User(1));
+        assert_eq!(a.value_fingerprint(), b.value_fingerprint());
+
+        let lazy = with_lazy_value(background(), || 1.0);
+        let before = lazy.value_fingerprint();
+        assert_eq!(lazy.value::<f64>(), Some(1.0));
+        assert_eq!(before, lazy.value_fingerprint());
+    }
+}
@@ -0,0 +1,109 @@
+use {CancelFn, Context, ContextError, Done, ErrorDetail, InnerContext, Signal, WithCancel};
+use futures::{Future, Poll};
+
+/// A context node sharing its parent with other nodes. Since the parent is not uniquely owned,
+/// it is observed via `Context::done` instead of being polled.
+struct SharedParent {
+    parent: Context,
+    done: Done,
+}
+
+impl InnerContext for SharedParent {
+    fn parent(&self) -> Option<&Context> {
+        Some(&self.parent)
+    }
+
+    fn kind(&self) -> &'static str {
+        "group"
+    }
+
+    fn poll_detail_ref(&self) -> Poll<(), ErrorDetail> {
+        let source_kind = self.kind();
+        self.done
+            .poll_ref()
+            .map_err(|error| ErrorDetail { error, source_kind })
+    }
+}
+
+impl Future for SharedParent {
+    type Item = ();
+    type Error = ContextError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.done.poll()
+    }
+}
+
+/// Returns `n` children of the given parent, together with a single cancel function canceling
+/// all of them at once. The children share the parent, i.e. they resolve its values and deadline
+/// and are canceled once the parent is. This is useful for fan-out, where the coordinator cancels
+/// all outstanding work on the first failure.
+///
+/// Only cancelation of parent nodes exposing a `Signal` is observed (see `Context::done`).
+///
+/// # Examples
+///
+/// ```
+/// extern crate ctx;
+/// extern crate futures;
+///
+/// use ctx::{group, with_value, background, ContextError};
+/// use futures::Future;
+///
+/// fn main() {
+///     let (children, cancel) = group(with_value(background(), 42), 3);
+///     assert_eq!(children[2].value(), Some(42));
+///
+///     cancel();
+///     for child in children {
+///         assert_eq!(child.wait(), Err(ContextError::Canceled));
+///     }
+/// }
+/// ```
+pub fn group(parent: Context, n: usize) -> (Vec<Context>, CancelFn) {
+    let done = parent.done();
+    let signal = Signal::new();
+    let children = (0..n)
+        .map(|_| {
+            let shared = Context::new(SharedParent {
+                parent: parent.clone(),
+                done: done.clone(),
+            });
+            Context::new(WithCancel::new(shared, signal.clone(), false))
+        })
+        .collect();
+    let cancel = Box::new(move || signal.fire());
+    (children, cancel)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use futures::Future;
+    use group::group;
+    use {background, with_cancel, with_timeout, ContextError};
+
+    #[test]
+    fn group_cancel_test() {
+        let (children, cancel) = group(background(), 3);
+        assert_eq!(children.len(), 3);
+        cancel();
+        for child in children {
+            assert_eq!(child.wait(), Err(ContextError::Canceled));
+        }
+    }
+
+    #[test]
+    fn group_parent_test() {
+        let (parent, cancel) = with_cancel(background());
+        let (parent, _) = with_timeout(parent, Duration::from_secs(10));
+        let deadline = parent.deadline();
+        let (children, _) = group(parent, 2);
+        assert_eq!(children[0].deadline(), deadline);
+
+        cancel();
+        for child in children {
+            assert_eq!(child.wait(), Err(ContextError::Canceled));
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::time::Duration;
+use {with_cancel, with_timeout, CancelFn, Context};
+
+/// Parses a timeout in the format of the gRPC `grpc-timeout` header, i.e. up to eight digits
+/// followed by a unit (`H` for hours, `M` for minutes, `S` for seconds, `m` for milliseconds, `u`
+/// for microseconds or `n` for nanoseconds). Returns `None` for malformed input.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use ctx::parse_grpc_timeout;
+///
+/// assert_eq!(parse_grpc_timeout("100m"), Some(Duration::from_millis(100)));
+/// assert_eq!(parse_grpc_timeout("1.5S"), None);
+/// ```
+pub fn parse_grpc_timeout(s: &str) -> Option<Duration> {
+    if s.len() < 2 || s.len() > 9 {
+        return None;
+    }
+    let (digits, unit) = s.split_at(s.len() - 1);
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let value: u64 = digits.parse().ok()?;
+    match unit {
+        "H" => Some(Duration::from_secs(value * 60 * 60)),
+        "M" => Some(Duration::from_secs(value * 60)),
+        "S" => Some(Duration::from_secs(value)),
+        "m" => Some(Duration::from_millis(value)),
+        "u" => Some(Duration::from_micros(value)),
+        "n" => Some(Duration::from_nanos(value)),
+        _ => None,
+    }
+}
+
+/// Returns a copy of the parent context with the timeout of the given `grpc-timeout` header value
+/// applied (see `parse_grpc_timeout`), as a gRPC server does for each request. A malformed header
+/// value is ignored, i.e. the returned context is only cancelable then.
+pub fn with_grpc_timeout(parent: Context, header: &str) -> (Context, CancelFn) {
+    match parse_grpc_timeout(header) {
+        Some(timeout) => with_timeout(parent, timeout),
+        None => with_cancel(parent),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use grpc::{parse_grpc_timeout, with_grpc_timeout};
+    use {background, with_timeout};
+
+    #[test]
+    fn parse_grpc_timeout_test() {
+        assert_eq!(parse_grpc_timeout("2H"), Some(Duration::from_secs(7200)));
+        assert_eq!(parse_grpc_timeout("5M"), Some(Duration::from_secs(300)));
+        assert_eq!(parse_grpc_timeout("1S"), Some(Duration::from_secs(1)));
+        assert_eq!(parse_grpc_timeout("100m"), Some(Duration::from_millis(100)));
+        assert_eq!(parse_grpc_timeout("250u"), Some(Duration::from_micros(250)));
+        assert_eq!(parse_grpc_timeout("99999999n"), Some(Duration::from_nanos(99_999_999)));
+    }
+
+    #[test]
+    fn parse_grpc_timeout_garbage_test() {
+        for garbage in &["", "m", "100", "100x", "-1S", "1.5S", " 1S", "123456789S", "1s"] {
+            assert_eq!(parse_grpc_timeout(garbage), None, "{:?}", garbage);
+        }
+    }
+
+    #[test]
+    fn with_grpc_timeout_test() {
+        let (ctx, _) = with_grpc_timeout(background(), "200m");
+        assert!(ctx.deadline_is_about(Duration::from_millis(200), Duration::from_millis(20)));
+
+        let (parent, _) = with_timeout(background(), Duration::from_secs(10));
+        let deadline = parent.deadline();
+        let (ctx, _) = with_grpc_timeout(parent, "garbage");
+        assert_eq!(ctx.deadline(), deadline);
+    }
+}
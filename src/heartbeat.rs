@@ -0,0 +1,69 @@
+use std::time::Duration;
+use {Context, ContextError, Done};
+use futures::{Async, Future, Poll, Stream};
+use tokio_timer::Interval;
+use timer::timer;
+
+/// A stream that yields every interval until its context is done. Created via
+/// `Context::heartbeat`.
+pub struct Heartbeat {
+    interval: Interval,
+    done: Done,
+    finished: bool,
+}
+
+impl Context {
+    /// Returns a stream that yields every `interval` until the context is canceled or its
+    /// deadline is exceeded, at which point the stream fails with the context's error and
+    /// terminates.
+    pub fn heartbeat(&self, interval: Duration) -> Heartbeat {
+        Heartbeat {
+            interval: timer().interval(interval),
+            done: self.done(),
+            finished: false,
+        }
+    }
+}
+
+impl Stream for Heartbeat {
+    type Item = ();
+    type Error = ContextError;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if self.finished {
+            return Ok(Async::Ready(None));
+        }
+        if let Err(err) = self.done.poll() {
+            self.finished = true;
+            return Err(err);
+        }
+        self.interval.poll().map_err(|_| {
+            self.finished = true;
+            ContextError::DeadlineTooLong
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use futures::{Future, Stream};
+    use {background, with_timeout, ContextError};
+
+    #[test]
+    fn heartbeat_test() {
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(1000));
+
+        let mut ticks = 0;
+        let result = ctx
+            .heartbeat(Duration::from_millis(200))
+            .for_each(|_| {
+                ticks += 1;
+                Ok(())
+            })
+            .wait();
+
+        assert_eq!(result.unwrap_err(), ContextError::DeadlineExceeded);
+        assert!((3..=10).contains(&ticks), "unexpected number of ticks: {}", ticks);
+    }
+}
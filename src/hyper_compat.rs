@@ -0,0 +1,77 @@
+use std::time::Duration;
+use hyper::Request;
+use {background, parse_grpc_timeout, with_timeout, Context};
+
+/// The name of the request header used to pass the remaining time of a context (in
+/// milliseconds) to a server, if the `grpc-timeout` header is not set.
+pub const TIMEOUT_HEADER: &str = "ctx-timeout-ms";
+
+impl Context {
+    /// Creates a top-level context for the given request, whose deadline is read from the
+    /// `grpc-timeout` header, or from the `ctx-timeout-ms` header, or is `default` from now if
+    /// neither of them is set or valid. The context is stored in the request's extensions, so
+    /// that it can be retrieved via `Context::from_extensions`. Requires the `hyper` feature.
+    pub fn from_request<B>(req: &mut Request<B>, default: Duration) -> Context {
+        let header = |name: &str| req.headers().get(name).and_then(|val| val.to_str().ok());
+        let timeout = header("grpc-timeout")
+            .and_then(parse_grpc_timeout)
+            .or_else(|| {
+                header(TIMEOUT_HEADER)
+                    .and_then(|ms| ms.parse().ok())
+                    .map(Duration::from_millis)
+            })
+            .unwrap_or(default);
+
+        let (ctx, _) = with_timeout(background(), timeout);
+        req.extensions_mut().insert(ctx.clone());
+        ctx
+    }
+
+    /// Returns the context created for the given request via `Context::from_request`, or `None`
+    /// if there is none. Requires the `hyper` feature.
+    pub fn from_extensions<B>(req: &Request<B>) -> Option<&Context> {
+        req.extensions().get::<Context>()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use hyper::Request;
+    use hyper_compat::TIMEOUT_HEADER;
+    use Context;
+
+    #[test]
+    fn grpc_timeout_header_test() {
+        let mut req = Request::builder()
+            .header("grpc-timeout", "100m")
+            .body(())
+            .unwrap();
+        let ctx = Context::from_request(&mut req, Duration::from_secs(10));
+        assert!(ctx.deadline_is_about(Duration::from_millis(100), Duration::from_millis(20)));
+
+        let restored = Context::from_extensions(&req).unwrap();
+        assert_eq!(restored.deadline(), ctx.deadline());
+    }
+
+    #[test]
+    fn timeout_header_test() {
+        let mut req = Request::builder()
+            .header(TIMEOUT_HEADER, "2000")
+            .body(())
+            .unwrap();
+        let ctx = Context::from_request(&mut req, Duration::from_secs(10));
+        assert!(ctx.deadline_is_about(Duration::from_secs(2), Duration::from_millis(20)));
+    }
+
+    #[test]
+    fn default_timeout_test() {
+        let mut req = Request::builder()
+            .header("grpc-timeout", "garbage")
+            .body(())
+            .unwrap();
+        assert!(Context::from_extensions(&req).is_none());
+        let ctx = Context::from_request(&mut req, Duration::from_secs(10));
+        assert!(ctx.deadline_is_about(Duration::from_secs(10), Duration::from_millis(20)));
+    }
+}
@@ -0,0 +1,163 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use {Context, ContextError, ErrorDetail, InnerContext, Signal};
+use futures::{Async, Future, Poll};
+use timer::SharedDeadline;
+use lock::lock;
+
+pub struct WithIdle {
+    parent: Context,
+    signal: Signal,
+    idle: Duration,
+    last_activity: Arc<Mutex<Instant>>,
+    /// The end of the idle window the timer is currently set for.
+    deadline: Mutex<(Instant, SharedDeadline)>,
+}
+
+/// The handle used to signal activity to a context created via `with_idle`. It also allows to
+/// cancel the context explicitly.
+#[derive(Clone)]
+pub struct IdleGuard {
+    last_activity: Arc<Mutex<Instant>>,
+    signal: Signal,
+}
+
+impl IdleGuard {
+    /// Records activity, which resets the idle window.
+    pub fn touch(&self) {
+        *lock(&self.last_activity) = Instant::now();
+    }
+
+    /// Cancels the context.
+    pub fn cancel(&self) {
+        self.signal.fire()
+    }
+}
+
+impl WithIdle {
+    /// Checks whether the idle window elapsed, and if not, makes sure the current task is
+    /// notified once it does.
+    fn poll_idle(&self) -> Poll<(), ErrorDetail> {
+        if !self.signal.is_fired() {
+            let expires = *lock(&self.last_activity) + self.idle;
+            let mut deadline = lock(&self.deadline);
+            if deadline.0 != expires {
+                *deadline = (expires, SharedDeadline::new(expires));
+            }
+            match deadline.1.poll() {
+                Ok(Async::NotReady) => {
+                    // get notified in case the guard cancels the context
+                    self.signal.register();
+                    if !self.signal.is_fired() {
+                        return Ok(Async::NotReady);
+                    }
+                }
+                _ => self.signal.fire(),
+            }
+        }
+
+        Err(ErrorDetail {
+            error: ContextError::Canceled,
+            source_kind: self.kind(),
+        })
+    }
+}
+
+impl InnerContext for WithIdle {
+    fn parent(&self) -> Option<&Context> {
+        Some(&self.parent)
+    }
+
+    fn signal(&self) -> Option<&Signal> {
+        Some(&self.signal)
+    }
+
+    fn kind(&self) -> &'static str {
+        "with_idle"
+    }
+
+    fn poll_detail(&mut self) -> Poll<(), ErrorDetail> {
+        self.poll_idle()?;
+        self.parent.poll_detail()
+    }
+
+    fn poll_detail_ref(&self) -> Poll<(), ErrorDetail> {
+        self.poll_idle()?;
+        self.parent.0.poll_detail_ref()
+    }
+}
+
+impl Future for WithIdle {
+    type Item = ();
+    type Error = ContextError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.poll_detail().map_err(|detail| detail.error)
+    }
+}
+
+/// Returns a copy of the parent context, which is canceled once no activity has been recorded
+/// via the returned guard's `touch` for the given `idle` duration, e.g. to close idle
+/// connections. Contrary to a deadline, the context fails with `ContextError::Canceled`.
+///
+/// The idle window is checked when the context is polled, i.e. observers created via
+/// `Context::done` only notice the cancelation once the context itself has been polled.
+pub fn with_idle(parent: Context, idle: Duration) -> (Context, IdleGuard) {
+    let now = Instant::now();
+    let expires = now + idle;
+    let signal = Signal::new();
+    let last_activity = Arc::new(Mutex::new(now));
+    let guard = IdleGuard {
+        last_activity: last_activity.clone(),
+        signal: signal.clone(),
+    };
+    let ctx = WithIdle {
+        parent,
+        signal,
+        idle,
+        last_activity,
+        deadline: Mutex::new((expires, SharedDeadline::new(expires))),
+    };
+    (Context::new(ctx), guard)
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use futures::{Async, Future};
+    use futures::future::poll_fn;
+    use idle::with_idle;
+    use {background, ContextError};
+
+    #[test]
+    fn idle_test() {
+        let (mut ctx, guard) = with_idle(background(), Duration::from_millis(300));
+        let start = Instant::now();
+        let toucher = thread::spawn(move || {
+            while start.elapsed() < Duration::from_millis(600) {
+                guard.touch();
+                thread::sleep(Duration::from_millis(20));
+            }
+        });
+
+        thread::sleep(Duration::from_millis(500));
+        let result = poll_fn(|| Ok::<_, ()>(Async::Ready(ctx.poll()))).wait().unwrap();
+        assert_eq!(result, Ok(Async::NotReady));
+
+        toucher.join().unwrap();
+        let idle_since = Instant::now();
+        assert_eq!(ctx.wait(), Err(ContextError::Canceled));
+        assert!(idle_since.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn idle_cancel_test() {
+        let (ctx, guard) = with_idle(background(), Duration::from_secs(10));
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            guard.cancel();
+        });
+        assert_eq!(ctx.wait(), Err(ContextError::Canceled));
+    }
+}
@@ -0,0 +1,112 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use {Context, InnerContext, ContextError, ErrorDetail};
+use futures::{Future, Poll};
+use lock::lock;
+
+pub struct WithInstrumentation {
+    parent: Context,
+    counts: AccessCounts,
+}
+
+/// The number of value lookups per value type counted by a context created via
+/// `Context::instrument_values`.
+#[derive(Default)]
+pub struct AccessCounts(Mutex<HashMap<TypeId, u64>>);
+
+impl InnerContext for WithInstrumentation {
+    fn value_of(&self, type_id: TypeId) -> Option<&dyn Any> {
+        *lock(&self.counts.0).entry(type_id).or_insert(0) += 1;
+        None
+    }
+
+    fn access_counts(&self) -> Option<&AccessCounts> {
+        Some(&self.counts)
+    }
+
+    fn parent(&self) -> Option<&Context> {
+        Some(&self.parent)
+    }
+
+    fn kind(&self) -> &'static str {
+        "instrument_values"
+    }
+
+    fn poll_detail(&mut self) -> Poll<(), ErrorDetail> {
+        self.parent.poll_detail()
+    }
+}
+
+impl Future for WithInstrumentation {
+    type Item = ();
+    type Error = ContextError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.parent.poll_inner()
+    }
+}
+
+impl Context {
+    /// Wraps the context so that value lookups passing through it are counted per value type.
+    /// The counts can be retrieved using `value_access_counts`. This helps to find values that
+    /// are never used.
+    ///
+    /// Only lookups that are not already satisfied by contexts derived from the returned one are
+    /// counted.
+    pub fn instrument_values(self) -> Context {
+        Context::new(WithInstrumentation {
+            parent: self,
+            counts: AccessCounts::default(),
+        })
+    }
+
+    /// Returns the number of lookups per value type counted by the closest context created via
+    /// `instrument_values`. Returns an empty map if the context is not instrumented.
+    pub fn value_access_counts(&self) -> HashMap<TypeId, u64> {
+        self.ancestors()
+            .find_map(|ctx| ctx.0.access_counts())
+            .map(|counts| lock(&counts.0).clone())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::any::TypeId;
+    use instrument::AccessCounts;
+    use {background, strip_values, with_value};
+
+    #[test]
+    fn access_counts_test() {
+        let ctx = with_value(background(), 42);
+        let ctx = with_value(ctx, 1.0).instrument_values();
+
+        for _ in 0..3 {
+            assert_eq!(ctx.value(), Some(42));
+        }
+        assert_eq!(ctx.value_ref::<String>(), None);
+
+        let counts = ctx.value_access_counts();
+        assert_eq!(counts.get(&TypeId::of::<i32>()), Some(&3));
+        assert_eq!(counts.get(&TypeId::of::<String>()), Some(&1));
+        assert_eq!(counts.get(&TypeId::of::<f64>()), None);
+    }
+
+    #[test]
+    fn hidden_counts_test() {
+        let ctx = with_value(background(), 42).instrument_values();
+        assert_eq!(ctx.value(), Some(42));
+        assert!(ctx.value_ref::<AccessCounts>().is_none());
+
+        // the counts stay accessible below a filter, which hides values only
+        let (ctx, _) = strip_values(ctx);
+        let counts = ctx.value_access_counts();
+        assert_eq!(counts.get(&TypeId::of::<i32>()), Some(&1));
+    }
+
+    #[test]
+    fn not_instrumented_test() {
+        assert!(with_value(background(), 42).value_access_counts().is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+use {Context, ContextError};
+
+/// The handle passed to the closure run by `Context::run_interruptible`, which allows it to check
+/// whether it should stop early.
+pub struct InterruptCheck<'a> {
+    ctx: &'a Context,
+}
+
+impl<'a> InterruptCheck<'a> {
+    /// Returns whether the context is done, in which case the closure should return `None` as
+    /// soon as possible. Meant to be called at loop boundaries of long-running synchronous work.
+    pub fn should_stop(&self) -> bool {
+        self.ctx.terminal_reason().is_some()
+    }
+}
+
+impl Context {
+    /// Runs the given closure for synchronous (e.g. CPU-bound) work until it returns `Some` or the
+    /// context is done, whichever happens first. The closure is expected to process the work in
+    /// steps, to call `InterruptCheck::should_stop` in between, and to return `None` if it should
+    /// stop or if it wants to yield before continuing with the next step. It is called again
+    /// (unless the context is done) whenever it returns `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctx::background;
+    ///
+    /// let mut sum = 0u64;
+    /// let mut next = 0u64;
+    /// let result = background().run_interruptible(|check| {
+    ///     while next < 1_000 {
+    ///         if check.should_stop() {
+    ///             return None;
+    ///         }
+    ///         sum += next;
+    ///         next += 1;
+    ///     }
+    ///     Some(sum)
+    /// });
+    /// assert_eq!(result, Ok(499_500));
+    /// ```
+    pub fn run_interruptible<T, F>(&self, mut f: F) -> Result<T, ContextError>
+    where
+        F: FnMut(&InterruptCheck) -> Option<T>,
+    {
+        let check = InterruptCheck { ctx: self };
+        loop {
+            if let Some(err) = self.terminal_reason() {
+                return Err(err);
+            }
+            if let Some(val) = f(&check) {
+                return Ok(val);
+            }
+        }
+    }
+
+    /// Returns a guard for paginated operations, which returns `true` as long as the context is
+    /// live and `false` once it is done (e.g. once its deadline expired). The check does not
+    /// block. Once the guard returned `false`, it keeps returning `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use ctx::{background, with_timeout};
+    ///
+    /// let (ctx, _) = with_timeout(background(), Duration::from_secs(10));
+    /// let mut next_page = ctx.pagination_guard();
+    /// let mut pages = Vec::new();
+    /// while next_page() && pages.len() < 3 {
+    ///     pages.push(pages.len());
+    /// }
+    /// assert_eq!(pages, vec![0, 1, 2]);
+    /// ```
+    pub fn pagination_guard(&self) -> impl FnMut() -> bool + '_ {
+        let mut live = true;
+        move || {
+            live = live && self.terminal_reason().is_none();
+            live
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+    use std::time::Duration;
+    use {already_done, background, with_cancel, with_timeout, ContextError};
+
+    #[test]
+    fn run_interruptible_cancel_test() {
+        let (ctx, cancel) = with_cancel(background());
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            cancel();
+        });
+
+        let mut iterations = 0u64;
+        let result = ctx.run_interruptible(|check| -> Option<()> {
+            loop {
+                if check.should_stop() {
+                    return None;
+                }
+                iterations += 1;
+            }
+        });
+        assert_eq!(result, Err(ContextError::Canceled));
+        assert!(iterations > 0);
+    }
+
+    #[test]
+    fn run_interruptible_done_test() {
+        let ctx = already_done(ContextError::DeadlineExceeded);
+        let result = ctx.run_interruptible(|_| -> Option<()> { panic!("must not be called") });
+        assert_eq!(result, Err(ContextError::DeadlineExceeded));
+    }
+
+    #[test]
+    fn pagination_guard_test() {
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(200));
+        let mut next_page = ctx.pagination_guard();
+        assert!(next_page());
+        assert!(next_page());
+
+        thread::sleep(Duration::from_millis(300));
+        assert!(!next_page());
+        assert!(!next_page());
+    }
+}
@@ -10,50 +10,682 @@
 
 extern crate futures;
 extern crate tokio_timer;
+#[cfg(all(feature = "signal", unix))]
+extern crate signal_hook;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+#[cfg(feature = "slog")]
+#[macro_use]
+extern crate slog;
+#[cfg(feature = "hyper")]
+extern crate hyper;
+#[cfg(feature = "axum")]
+extern crate http;
+#[cfg(feature = "axum")]
+extern crate axum_core;
+#[cfg(feature = "actix")]
+extern crate actix_web;
+#[cfg(feature = "ctrlc")]
+extern crate ctrlc;
 
-use std::any::Any;
+use std::any::{type_name, Any, TypeId};
+use std::collections::HashSet;
 use std::error::Error;
 use std::fmt;
-use std::time::Instant;
-use futures::{Future, Poll};
+use std::hash::Hasher;
+use std::io;
+use std::sync::{Arc, OnceLock, Weak};
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(debug_assertions)]
+use std::sync::Once;
+use std::time::{Duration, Instant};
+use futures::{Async, Future, Poll};
+use timer::{schedule, timer};
+use cache::ValueCache;
+use instrument::AccessCounts;
 
 mod with_value;
 mod with_cancel;
 mod with_deadline;
-pub use with_value::{WithValue, with_value};
-pub use with_cancel::{WithCancel, with_cancel};
-pub use with_deadline::{WithDeadline, with_deadline, with_timeout};
+mod with_value_filter;
+mod with_lazy_value;
+mod with_work_limit;
+mod signal;
+mod done;
+mod channel;
+mod heartbeat;
+mod cancel_stream;
+mod process;
+#[macro_use]
+mod value_as;
+mod budget;
+mod instrument;
+#[macro_use]
+mod combinators;
+mod tags;
+mod fingerprint;
+mod metadata;
+mod borrowed;
+mod group;
+mod cache;
+mod attempt;
+mod lock;
+mod idle;
+mod priority;
+mod bound_io;
+mod cancel_while;
+mod merge;
+mod periodic;
+mod testing;
+mod revocable;
+mod named;
+mod interrupt;
+mod builder;
+mod grpc;
+mod value_stack;
+#[cfg(feature = "tokio")]
+mod tokio_compat;
+#[cfg(feature = "slog")]
+mod logger;
+#[cfg(feature = "hyper")]
+mod hyper_compat;
+#[cfg(feature = "axum")]
+mod axum_compat;
+#[cfg(feature = "actix")]
+mod actix_compat;
+#[cfg(feature = "strict-values")]
+#[macro_use]
+mod strict;
+#[cfg(all(feature = "signal", unix))]
+mod os_signal;
+#[cfg(feature = "ctrlc")]
+mod ctrl_c;
+mod timer;
+pub use with_value::{WithValue, with_value, map_value, set_value_checked, with_value_arc,
+                     with_value_into};
+pub use with_cancel::{WithCancel, AlreadyDone, already_done, with_cancel, with_cancel_drop,
+                      with_cancel_from, with_cancel_observed};
+pub use with_deadline::{WithDeadline, WithMappedDeadline, with_deadline, with_timeout,
+                        with_timeout_at, tighten_deadline, map_deadline, with_soft_deadline,
+                        with_coalesced_timeout, with_timeout_anchored, set_max_timeout};
+pub use with_value_filter::{WithValueFilter, with_value_filter, strip_values};
+pub use with_lazy_value::{WithLazyValue, with_lazy_value};
+pub use with_work_limit::{WithWorkLimit, WorkToken, with_work_limit};
+pub use signal::Signal;
+pub use done::{Done, SharedDone};
+pub use channel::{Sender, Receiver, SendError};
+pub use heartbeat::Heartbeat;
+pub use cancel_stream::CancelStream;
+pub use process::DEADLINE_ENV_VAR;
+pub use value_as::register_trait;
+pub use budget::{Budget, Phase, PhaseUsage};
+pub use instrument::WithInstrumentation;
+pub use timer::shutdown_timer;
+pub use tags::with_tag;
+pub use combinators::{or_default, with_handler_timeout, Selected};
+#[doc(hidden)]
+pub use combinators::select_futures;
+pub use fingerprint::{WithHashableValue, with_hashable_value};
+pub use metadata::with_metadata;
+pub use borrowed::{BorrowedCtx, ScopedContext, ScopedKey, borrow_and_derive};
+pub use group::group;
+pub use cache::WithValueCache;
+pub use attempt::with_attempt;
+pub use lock::{LockPolicy, set_lock_policy};
+pub use idle::{WithIdle, IdleGuard, with_idle};
+pub use priority::{Priority, with_priority};
+pub use bound_io::BoundIo;
+pub use cancel_while::{WithCancelWhile, with_cancel_while};
+pub use merge::{MergedContexts, MergedValues, merge_all, merge_values};
+pub use testing::assert_propagates_cancellation;
+pub use revocable::{RevocableCancel, with_revocable_cancel};
+pub use named::{WithNamedValue, with_named_value};
+pub use interrupt::InterruptCheck;
+pub use builder::ContextBuilder;
+pub use grpc::{parse_grpc_timeout, with_grpc_timeout};
+pub use value_stack::ValueGuard;
+#[cfg(all(feature = "signal", unix))]
+pub use os_signal::cancel_on_signal;
+#[cfg(feature = "ctrlc")]
+pub use ctrl_c::cancel_on_ctrlc;
+#[cfg(feature = "slog")]
+pub use logger::with_logger;
+#[cfg(feature = "hyper")]
+pub use hyper_compat::TIMEOUT_HEADER;
+#[cfg(feature = "strict-values")]
+pub use strict::{declare_value, set_strict_values};
 
-pub struct Context(pub Box<InnerContext<Item = (), Error = ContextError>>);
+/// The function returned alongside derived contexts, which cancels the context when called.
+pub type CancelFn = Box<dyn Fn() + Send>;
+
+/// A context carrying a deadline, a cancelation signal, and request-scoped values.
+///
+/// The chain of context nodes is reference-counted, so contexts are cheap to clone and can be
+/// shared across threads. Clones share the same chain: they observe the same cancelation and
+/// resolve the same (read-only) values and deadline. Contexts that are not uniquely owned are
+/// polled through a shared reference (see `poll_ref`).
+///
+/// Polling a context that is done for multiple reasons at once resolves with a deterministic
+/// error: each node checks its own cancelation first, then its own deadline, and only then its
+/// parent. E.g. a context created via `with_timeout` that has been canceled after its deadline
+/// expired resolves with `ContextError::Canceled`, but if only its parent has been canceled, it
+/// resolves with `ContextError::DeadlineExceeded`. Note that `Context::terminal_reason` and
+/// `Context::done` do not poll the chain and report any cancelation over an exceeded deadline.
+#[derive(Clone)]
+pub struct Context(pub Arc<dyn InnerContext<Item = (), Error = ContextError>>);
+
+/// An extension of the deadline of a context, which is reverted once dropped. Created via
+/// `Context::extend_deadline`.
+#[must_use = "the deadline extension is reverted once dropped"]
+pub struct DeadlineExtension {
+    node: Option<Context>,
+    extra: Duration,
+}
+
+impl Drop for DeadlineExtension {
+    fn drop(&mut self) {
+        if let Some(node) = &self.node {
+            node.0.revert_deadline_extension(self.extra);
+        }
+    }
+}
+
+/// A handle to a context that does not keep it alive. Created via `Context::downgrade`.
+pub struct WeakContext(Weak<dyn InnerContext<Item = (), Error = ContextError>>);
+
+impl WeakContext {
+    /// Returns the context, or `None` if all handles to it have been dropped.
+    pub fn upgrade(&self) -> Option<Context> {
+        self.0.upgrade().map(Context)
+    }
+}
 
 impl Context {
     pub fn new<C: 'static + InnerContext>(ctx: C) -> Self {
-        Context(Box::new(ctx))
+        Context(Arc::new(ctx))
+    }
+
+    /// Returns a handle to the context that does not keep it alive, e.g. to keep track of
+    /// in-flight requests in a registry without leaking completed ones.
+    ///
+    /// Like clones, weak handles cause the context to be polled through a shared reference (see
+    /// `poll_ref`) as long as they exist.
+    pub fn downgrade(&self) -> WeakContext {
+        WeakContext(Arc::downgrade(&self.0))
+    }
+
+    /// Polls the context without the debug warning for background contexts. Falls back to
+    /// polling through a shared reference if the context is not uniquely owned.
+    pub(crate) fn poll_inner(&mut self) -> Poll<(), ContextError> {
+        match Arc::get_mut(&mut self.0) {
+            Some(inner) => inner.poll(),
+            None => self.poll_ref(),
+        }
     }
 
+    /// Returns the effective deadline of the context, which is the earliest deadline among the
+    /// context and its ancestors.
     pub fn deadline(&self) -> Option<Instant> {
-        self.0.deadline()
+        let parent = self
+            .0
+            .parent()
+            .filter(|_| self.0.inherits_deadline())
+            .and_then(|parent| parent.deadline());
+        match (self.0.deadline(), parent) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Returns the next instant at which the context should be checked again when scheduling work
+    /// on a timer, which is its effective deadline. Cancelation is not bound to a point in time,
+    /// so contexts that can only be canceled (but have no deadline) return `None`.
+    pub fn next_wakeup(&self) -> Option<Instant> {
+        self.deadline()
+    }
+
+    /// Returns the time remaining until the effective deadline, or `None` if the context has no
+    /// deadline. An expired deadline results in a zero duration.
+    pub fn deadline_remaining(&self) -> Option<Duration> {
+        self.remaining_at(Instant::now())
+    }
+
+    /// Same as `deadline_remaining`, but computes the time remaining at the given instant instead
+    /// of now, e.g. to reason about deadlines deterministically in tests and schedulers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::{Duration, Instant};
+    /// use ctx::{root_at, with_timeout_anchored};
+    ///
+    /// let start = Instant::now();
+    /// let (ctx, _) = with_timeout_anchored(root_at(start), Duration::from_secs(10), Some(start));
+    /// let later = start + Duration::from_secs(4);
+    /// assert_eq!(ctx.remaining_at(later), Some(Duration::from_secs(6)));
+    /// ```
+    pub fn remaining_at(&self, now: Instant) -> Option<Duration> {
+        self.deadline()
+            .map(|when| when.saturating_duration_since(now))
+    }
+
+    /// Returns whether the time remaining until the effective deadline is within `tolerance` of
+    /// `expected`, e.g. to assert in tests that a context has been created with the right
+    /// deadline. Returns `false` if the context has no deadline.
+    pub fn deadline_is_about(&self, expected: Duration, tolerance: Duration) -> bool {
+        self.deadline_remaining()
+            .is_some_and(|remaining| remaining.abs_diff(expected) <= tolerance)
+    }
+
+    /// Returns the time the root of the context chain (e.g. the `background()` context created at
+    /// the start of a request) has been created at, or `None` if the root does not track it.
+    pub fn created_at(&self) -> Option<Instant> {
+        self.ancestors().last().and_then(|root| root.0.created_at())
+    }
+
+    /// Returns the time elapsed since the root of the context chain has been created, e.g. to
+    /// track request latency. Returns a zero duration if the root does not track its creation
+    /// time.
+    pub fn elapsed(&self) -> Duration {
+        self.created_at()
+            .map_or(Duration::ZERO, |created_at| created_at.elapsed())
+    }
+
+    /// Returns how long to wait until the context is done, e.g. to be used as the timeout of a
+    /// `select`:
+    ///
+    /// - `Some(Duration::ZERO)` if the context is already done, i.e. canceled or its deadline is
+    ///   exceeded,
+    /// - `Some(d)` with the time until the effective deadline, if the context is still pending,
+    /// - `None` if the context is still pending and has no deadline.
+    pub fn until_fire(&self) -> Option<Duration> {
+        if self.terminal_reason().is_some() {
+            Some(Duration::ZERO)
+        } else {
+            self.deadline_remaining()
+        }
+    }
+
+    /// Returns the error the context resolved with, or `None` if it is still pending. Contrary to
+    /// polling the context, this neither registers the current task nor requires ownership, e.g.
+    /// for observers of a context that fired elsewhere. The reason of a canceled context is the
+    /// one recorded when its signal fired first.
+    pub fn terminal_reason(&self) -> Option<ContextError> {
+        self.signals()
+            .find_map(|(_, signal)| signal.error())
+            .or_else(|| self.deadline_remaining_or_err().err())
+    }
+
+    /// Returns the source of the cancelation of the context, i.e. the source recorded by the
+    /// cancel function of the closest canceled node (see `with_cancel_from`), or the node's kind
+    /// if it has been canceled without a source. Returns `None` if the context is not canceled.
+    pub fn cancel_source(&self) -> Option<&'static str> {
+        self.signals()
+            .find(|(_, signal)| signal.is_fired())
+            .map(|(ctx, signal)| signal.source().unwrap_or(ctx.0.kind()))
+    }
+
+    /// Returns whether the context has been canceled, i.e. whether its terminal reason (see
+    /// `terminal_reason`) is `ContextError::Canceled`.
+    pub fn is_canceled(&self) -> bool {
+        self.terminal_reason() == Some(ContextError::Canceled)
+    }
+
+    /// Returns the time at which the context was first observed to be done while polling it, or
+    /// `None` if polling it has not failed yet. Note that this is recorded at poll time, so it
+    /// reflects when the caller noticed the context being done, e.g. to measure actual latency.
+    pub fn completed_at(&self) -> Option<Instant> {
+        self.ancestors().filter_map(|ctx| ctx.0.completed_at()).min()
+    }
+
+    /// Same as `deadline_remaining`, but fails with `ContextError::DeadlineExceeded` if the
+    /// deadline has already passed, which allows to check for expiry and get the remaining time in
+    /// a single call.
+    pub fn deadline_remaining_or_err(&self) -> Result<Option<Duration>, ContextError> {
+        match self.deadline() {
+            Some(when) => {
+                let now = Instant::now();
+                if when <= now {
+                    Err(ContextError::DeadlineExceeded)
+                } else {
+                    Ok(Some(when - now))
+                }
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the timeout that should be used for calls to downstream services, which is the
+    /// remaining time minus the given `margin` reserved for processing the downstream response.
+    /// Returns `None` if the context has no deadline or if the margin exceeds the remaining time.
+    pub fn downstream_timeout(&self, margin: Duration) -> Option<Duration> {
+        self.deadline_remaining()
+            .and_then(|remaining| remaining.checked_sub(margin))
+            .filter(|timeout| *timeout > Duration::from_secs(0))
+    }
+
+    /// Returns the index of the first of the given ascending bucket bounds the original timeout of
+    /// the context falls into (i.e. is less than or equal to), e.g. to group requests by their
+    /// configured timeout in metrics. The original timeout is the one the context node providing
+    /// the effective deadline has been created with (see `with_timeout`).
+    ///
+    /// Returns `None` if the context has no deadline, if the original timeout is unknown, or if
+    /// it exceeds all buckets.
+    pub fn timeout_bucket(&self, buckets: &[Duration]) -> Option<usize> {
+        let timeout = self.deadline_node().and_then(|ctx| ctx.0.timeout())?;
+        buckets.iter().position(|bucket| timeout <= *bucket)
+    }
+
+    /// Returns the kind of the context node that imposed the effective deadline (see
+    /// `InnerContext::kind`), e.g. to debug why a request has a surprisingly short deadline.
+    /// Returns `None` if the context has no deadline.
+    pub fn deadline_source(&self) -> Option<&'static str> {
+        self.deadline_node().map(|ctx| ctx.0.kind())
+    }
+
+    /// Extends the deadline of the context node that imposed the effective deadline (see
+    /// `deadline_source`) by `extra` until the returned extension is dropped, e.g. to give a
+    /// sub-phase of a streaming response extra time without leaking it into the rest of the
+    /// request. The extension affects the context, all its clones and all contexts derived from
+    /// it. Deadlines of ancestors of that node still apply, so the effective deadline might be
+    /// extended by less than `extra`.
+    ///
+    /// `Done` futures created before the extension do not observe it. Nothing is extended if the
+    /// context has no deadline, or if the node imposing it does not support extensions (only
+    /// deadlines created by this crate do).
+    pub fn extend_deadline(&self, extra: Duration) -> DeadlineExtension {
+        let node = self
+            .deadline_node()
+            .filter(|node| node.0.extend_deadline(extra))
+            .cloned();
+        DeadlineExtension { node, extra }
+    }
+
+    /// Returns the closest context node holding the effective deadline.
+    fn deadline_node(&self) -> Option<&Context> {
+        let deadline = self.deadline()?;
+        self.ancestors()
+            .find(|ctx| ctx.0.deadline() == Some(deadline))
+    }
+
+    /// Returns a future that only consists of the timer portion of the context, i.e. it fails
+    /// with `ContextError::DeadlineExceeded` once the effective deadline expires, but does not
+    /// observe cancelation. Returns `None` if the context has no deadline.
+    pub fn deadline_future(&self) -> Option<impl Future<Item = (), Error = ContextError>> {
+        self.deadline_remaining().map(|timeout| {
+            timer()
+                .sleep(timeout)
+                .then(|result| -> Result<(), ContextError> {
+                    match result {
+                        Ok(_) => Err(ContextError::DeadlineExceeded),
+                        Err(_) => Err(ContextError::DeadlineTooLong),
+                    }
+                })
+        })
     }
 
     pub fn value<T>(&self) -> Option<T>
     where
         T: Any + Clone,
     {
-        self.0
-            .value()
-            .and_then(|val_any| val_any.downcast_ref::<T>())
-            .map(|v| (*v).clone())
-            .or_else(|| self.0.parent().and_then(|parent| parent.value()))
+        match self.0.value_cache() {
+            Some(cache) => {
+                let type_id = TypeId::of::<T>();
+                let node = cache.get_or_resolve(type_id, || {
+                    self.value_nodes(type_id)
+                        .find(|ctx| ctx.0.value_of(type_id).is_some_and(|val| val.is::<T>()))
+                        .cloned()
+                });
+                node.and_then(|node| node.0.value_of(type_id)?.downcast_ref::<T>().cloned())
+            }
+            None => self.values::<T>().next().cloned(),
+        }
     }
 
+    /// Same as `value`, but returns a reference instead of a clone. The reference borrows the
+    /// context, so it cannot outlive the chain it points into, e.g. once the context is moved into
+    /// a combinator:
+    ///
+    /// ```compile_fail
+    /// extern crate ctx;
+    /// extern crate futures;
+    ///
+    /// use ctx::{with_value, background};
+    /// use futures::Future;
+    /// use futures::future;
+    ///
+    /// fn main() {
+    ///     let ctx = with_value(background(), 42);
+    ///     let val = ctx.value_ref::<i32>().unwrap();
+    ///     let _ = ctx.select2(future::empty::<(), ()>());
+    ///     assert_eq!(*val, 42);
+    /// }
+    /// ```
     pub fn value_ref<T>(&self) -> Option<&T>
     where
         T: Any,
     {
-        self.0
-            .value()
-            .and_then(|val_any| val_any.downcast_ref::<T>())
-            .or_else(|| self.0.parent().and_then(|parent| parent.value()))
+        self.values::<T>().next()
+    }
+
+    /// Same as `value`, but returns a descriptive error naming the expected type if there is no
+    /// value of type `T`.
+    pub fn require_value<T>(&self) -> Result<T, MissingValue>
+    where
+        T: Any + Clone,
+    {
+        self.value().ok_or(MissingValue {
+            type_name: type_name::<T>(),
+        })
+    }
+
+    /// Returns the first value of type `T` among the context and its ancestors that satisfies the
+    /// given predicate.
+    pub fn find_value<T, F>(&self, pred: F) -> Option<T>
+    where
+        T: Any + Clone,
+        F: Fn(&T) -> bool,
+    {
+        self.values::<T>().find(|val| pred(val)).cloned()
+    }
+
+    /// Returns an iterator over all values of type `T` visible from this context, starting with
+    /// the closest one.
+    fn values<T>(&self) -> impl Iterator<Item = &T>
+    where
+        T: Any,
+    {
+        let type_id = TypeId::of::<T>();
+        self.value_nodes(type_id).filter_map(move |ctx| {
+            ctx.0
+                .value_of(type_id)
+                .and_then(|val_any| val_any.downcast_ref::<T>())
+        })
+    }
+
+    /// Returns an iterator over all nodes whose values of the given type are visible from this
+    /// context, starting with the closest one. Nodes of secondary chains (see `merge_values`)
+    /// follow after all other nodes.
+    fn value_nodes(&self, type_id: TypeId) -> Box<dyn Iterator<Item = &Context> + '_> {
+        self.lookup_nodes(Some(type_id))
+    }
+
+    /// Same as `value_nodes`, but for lookups of any type if `type_id` is `None`, i.e. without
+    /// stopping at nodes hiding values (see `InnerContext::forwards_value`). The nodes are
+    /// returned in the same order for all types.
+    fn lookup_nodes(&self, type_id: Option<TypeId>) -> Box<dyn Iterator<Item = &Context> + '_> {
+        let chain = move || {
+            let mut forward = true;
+            self.ancestors().take_while(move |ctx| {
+                let visit = forward;
+                forward = type_id.is_none_or(|type_id| ctx.0.forwards_value(type_id));
+                visit
+            })
+        };
+        let secondary = chain()
+            .filter_map(|ctx| ctx.0.secondary())
+            .flat_map(move |secondary| secondary.lookup_nodes(type_id));
+        Box::new(chain().chain(secondary))
+    }
+
+    /// Returns an iterator over the signals of the context's chain together with the nodes holding
+    /// them, starting with the closest one. The chains of merged contexts (see `merge_all`) are
+    /// walked right after the node merging them.
+    pub(crate) fn signals(&self) -> Box<dyn Iterator<Item = (&Context, &Signal)> + '_> {
+        Box::new(self.ancestors().flat_map(|ctx| {
+            let merged = ctx.0.merged_parents().iter().flat_map(Context::signals);
+            ctx.0.signal().map(|signal| (ctx, signal)).into_iter().chain(merged)
+        }))
+    }
+
+    /// Returns the nodes holding the values visible from this context together with the types of
+    /// their values, starting with the closest one. Only the closest value per type is visible,
+    /// i.e. shadowed values and values hidden by `with_value_filter` are skipped. Values of
+    /// secondary chains (see `merge_values`) are not included.
+    pub(crate) fn visible_values(&self) -> Vec<(TypeId, &Context)> {
+        let mut seen = HashSet::new();
+        let chain: Vec<&Context> = self.ancestors().collect();
+        chain
+            .iter()
+            .enumerate()
+            .filter_map(|(i, ctx)| {
+                let type_id = ctx.0.value_type()?;
+                let visible = chain[..i].iter().all(|c| c.0.forwards_value(type_id));
+                (visible && seen.insert(type_id)).then_some((type_id, *ctx))
+            })
+            .collect()
+    }
+
+    /// Returns whether this is a bare background context (see `background`), which never
+    /// resolves, i.e. whether its kind is `BACKGROUND_KIND`.
+    pub fn is_background(&self) -> bool {
+        self.0.kind() == BACKGROUND_KIND
+    }
+
+    /// Returns an iterator over the context itself, followed by its parent, the parent's parent
+    /// and so on.
+    pub fn ancestors(&self) -> Ancestors<'_> {
+        Ancestors { next: Some(self) }
+    }
+
+    /// Returns the number of nodes in the context's chain, including the context itself and the
+    /// top-level context (e.g. `background()`).
+    pub fn chain_len(&self) -> usize {
+        self.ancestors().count()
+    }
+
+    /// Returns the number of nodes the context has been derived by from its top-level context.
+    /// Note that some derivations add more than one node (e.g. `with_timeout`).
+    pub fn depth(&self) -> usize {
+        self.chain_len() - 1
+    }
+
+    /// Returns a human-readable summary of the state of the context, e.g. to dump in-flight
+    /// requests on an admin endpoint. It contains the time remaining until the deadline, whether
+    /// the context is done (and why), its depth, and the type names of its visible values (closest
+    /// first, see `value_fingerprint` for which values are visible).
+    ///
+    /// The format is meant for humans and may change at any time.
+    pub fn explain(&self) -> String {
+        let deadline = match self.deadline_remaining() {
+            Some(remaining) => format!("{:?} remaining", remaining),
+            None => "no deadline".to_string(),
+        };
+        let done = self.done().check().map_or("no", |err| err.as_str());
+        let values: Vec<&str> = self
+            .visible_values()
+            .into_iter()
+            .filter_map(|(_, ctx)| ctx.0.value_type_name())
+            .collect();
+        format!(
+            "deadline: {}, done: {}, depth: {}, values: [{}]",
+            deadline,
+            done,
+            self.depth(),
+            values.join(", ")
+        )
+    }
+
+    /// Decomposes the context into primitives suitable for passing across an FFI boundary: the
+    /// time remaining until its deadline (if any) and a flag that is set once the context is
+    /// canceled. Deadlines are not reflected by the flag; use the returned duration instead.
+    pub fn into_parts(self) -> (Option<Duration>, Arc<AtomicBool>) {
+        (self.deadline_remaining(), self.signal_flag())
+    }
+
+    /// Returns a flag that is set once the context is canceled or its deadline is exceeded, e.g.
+    /// for synchronous workers on a thread pool, which can check it cheaply and without locking.
+    pub fn cancel_flag(&self) -> Arc<AtomicBool> {
+        let flag = self.signal_flag();
+        if self.terminal_reason().is_some() {
+            flag.store(true, Ordering::SeqCst);
+        } else if let Some(deadline) = self.deadline() {
+            let flag = Arc::downgrade(&flag);
+            schedule(deadline, move || {
+                if let Some(flag) = flag.upgrade() {
+                    flag.store(true, Ordering::SeqCst);
+                }
+            });
+        }
+        flag
+    }
+
+    /// Returns a flag that is set once any signal of the context's chain fires.
+    fn signal_flag(&self) -> Arc<AtomicBool> {
+        let canceled = Arc::new(AtomicBool::new(false));
+        for (_, signal) in self.signals() {
+            signal.set_on_fire(&canceled);
+        }
+        canceled
+    }
+
+    /// Returns a future that resolves once the context is canceled or its deadline is exceeded,
+    /// without taking ownership of the context.
+    pub fn done(&self) -> Done {
+        Done::new(self)
+    }
+
+    /// Same as `done`, but returns a cloneable future, which allows many tasks to await the
+    /// completion of the context without each of them creating its own `Done` future.
+    /// Cancelable context nodes (e.g. the ones created by `with_cancel` and `with_timeout`) store
+    /// the future, so that all callers awaiting the same context share a single one.
+    pub fn shared_done(&self) -> SharedDone {
+        match self.0.shared_done() {
+            Some(slot) => slot.get_or_init(|| self.done().shared()).clone(),
+            None => self.done().shared(),
+        }
+    }
+
+    /// Converts the context into a future that fails with an `io::Error` instead of a
+    /// `ContextError`, for use with I/O code. See the `From<ContextError>` implementation of
+    /// `io::Error` for how errors are mapped.
+    pub fn into_io(self) -> impl Future<Item = (), Error = io::Error> {
+        self.map_err(io::Error::from)
+    }
+
+    /// Like `poll`, but additionally reports the kind of the context node that caused the context
+    /// to resolve.
+    pub fn poll_detail(&mut self) -> Poll<(), ErrorDetail> {
+        match Arc::get_mut(&mut self.0) {
+            Some(inner) => inner.poll_detail(),
+            None => self.0.poll_detail_ref(),
+        }
+    }
+
+    /// Polls only the deadline of the context, i.e. it fails with `ContextError::DeadlineExceeded`
+    /// once the effective deadline expires, but does not observe cancelation, e.g. for a rate
+    /// limiter that only cares about the time budget. Must be called from within a task context.
+    pub fn poll_deadline(&mut self) -> Poll<(), ContextError> {
+        self.0.poll_deadline()
+    }
+
+    /// Same as `poll`, but through a shared reference, so that a context can be observed while
+    /// it is borrowed elsewhere (e.g. to read values), and by multiple tasks at once. Each polling
+    /// task is notified once the context is done. Must be called from within a task context.
+    ///
+    /// Custom context nodes only take part if they implement `InnerContext::poll_detail_ref`.
+    pub fn poll_ref(&self) -> Poll<(), ContextError> {
+        self.0.poll_detail_ref().map_err(|detail| detail.error)
     }
 }
 
@@ -62,12 +694,40 @@ impl Future for Context {
     type Error = ContextError;
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        self.0.poll()
+        #[cfg(debug_assertions)]
+        {
+            if self.is_background() {
+                static WARNING: Once = Once::new();
+                WARNING.call_once(|| {
+                    eprintln!(
+                        "ctx: polling a background context, which never resolves (waiting on it \
+                         blocks forever)"
+                    )
+                });
+            }
+        }
+
+        self.poll_inner()
+    }
+}
+
+/// An iterator over a context and its ancestors. Created via `Context::ancestors`.
+pub struct Ancestors<'a> {
+    next: Option<&'a Context>,
+}
+
+impl<'a> Iterator for Ancestors<'a> {
+    type Item = &'a Context;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ctx = self.next?;
+        self.next = ctx.0.parent();
+        Some(ctx)
     }
 }
 
 /// A Context carries a deadline, a cancelation Future, and other values across API boundaries.
-pub trait InnerContext: Future<Item = (), Error = ContextError> {
+pub trait InnerContext: Future<Item = (), Error = ContextError> + Send + Sync {
     /// Returns the time when work done on behalf of this context should be
     /// canceled. Successive calls to deadline return the same result.
     fn deadline(&self) -> Option<Instant> {
@@ -79,46 +739,276 @@ pub trait InnerContext: Future<Item = (), Error = ContextError> {
     /// Context values should only be used for request-scoped data that transists
     /// processes and API boundaries and not for passing optional parameters to
     /// functions.
-    fn value(&self) -> Option<&Any> {
+    fn value(&self) -> Option<&dyn Any> {
+        None
+    }
+
+    /// Returns the value associated with this context, if it is of the given type. Nodes that
+    /// compute their value on demand can override this to only do so for lookups of their type.
+    fn value_of(&self, type_id: TypeId) -> Option<&dyn Any> {
+        self.value()
+            .filter(|val_any| Any::type_id(*val_any) == type_id)
+    }
+
+    /// Returns the value associated with this context under the given name (see
+    /// `with_named_value`).
+    fn named_value(&self, _name: &str) -> Option<&dyn Any> {
         None
     }
 
+    /// Returns the type of the value associated with this context, without computing it for nodes
+    /// that do so on demand.
+    fn value_type(&self) -> Option<TypeId> {
+        self.value().map(Any::type_id)
+    }
+
+    /// Returns the type name of the value associated with this context, if any, for diagnostic
+    /// purposes (see `Context::explain`).
+    fn value_type_name(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Feeds the value associated with this context into the given hasher, if it is hashable (see
+    /// `with_hashable_value`).
+    fn hash_value(&self, _state: &mut dyn Hasher) {}
+
     fn parent(&self) -> Option<&Context> {
         None
     }
+
+    /// Returns the contexts merged into this node besides its parent, whose cancelation applies
+    /// to this node as well (see `merge_all`). Their deadlines must be reflected by `deadline`
+    /// and `poll_deadline`.
+    fn merged_parents(&self) -> &[Context] {
+        &[]
+    }
+
+    /// Returns the timeout the deadline of this node has been created with, if known.
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Returns whether the deadlines of the ancestors apply to this context. Nodes replacing the
+    /// effective deadline (see `map_deadline`) return `false`.
+    fn inherits_deadline(&self) -> bool {
+        true
+    }
+
+    /// Returns whether value lookups for the given type should continue with the parent context,
+    /// if they could not be satisfied by this context.
+    fn forwards_value(&self, _type_id: TypeId) -> bool {
+        true
+    }
+
+    /// Polls the deadline of this node and its ancestors only, ignoring cancelation (see
+    /// `Context::poll_deadline`). Nodes with an own deadline must implement this through a shared
+    /// reference. The default forwards to the parent if the node inherits its deadline.
+    fn poll_deadline(&self) -> Poll<(), ContextError> {
+        match self.parent() {
+            Some(parent) if self.inherits_deadline() => parent.0.poll_deadline(),
+            _ => Ok(Async::NotReady),
+        }
+    }
+
+    /// Extends the deadline of this node by the given duration (see `Context::extend_deadline`).
+    /// Returns `false` if the node does not support extending its deadline.
+    fn extend_deadline(&self, _extra: Duration) -> bool {
+        false
+    }
+
+    /// Reverts an extension of the deadline of this node by the given duration, which has been
+    /// applied via `extend_deadline` before.
+    fn revert_deadline_extension(&self, _extra: Duration) {}
+
+    /// Returns the slot to store the future returned by `Context::shared_done` in, so that it is
+    /// shared by all callers. Nodes without a slot create a new future for each call.
+    fn shared_done(&self) -> Option<&OnceLock<SharedDone>> {
+        None
+    }
+
+    /// Returns the cache for value lookups on this node, if it has been created via
+    /// `Context::cached`.
+    fn value_cache(&self) -> Option<&ValueCache> {
+        None
+    }
+
+    /// Returns the value lookup counts of this node, if it has been created via
+    /// `Context::instrument_values`.
+    fn access_counts(&self) -> Option<&AccessCounts> {
+        None
+    }
+
+    /// Returns a context whose values are visible after the values of this node and its
+    /// ancestors (see `merge_values`).
+    fn secondary(&self) -> Option<&Context> {
+        None
+    }
+
+    /// Returns the signal that is fired once this node gets canceled, if it is cancelable. Used
+    /// to observe cancelation without polling the context itself (see `Context::done`).
+    fn signal(&self) -> Option<&Signal> {
+        None
+    }
+
+    /// Returns the time at which polling this node first failed with an error originating from
+    /// the node itself, if it did.
+    fn completed_at(&self) -> Option<Instant> {
+        None
+    }
+
+    /// Returns the time this node has been created at, if it is a root node tracking it.
+    fn created_at(&self) -> Option<Instant> {
+        None
+    }
+
+    /// Returns a short name describing the kind of this context node.
+    fn kind(&self) -> &'static str {
+        "context"
+    }
+
+    /// Like `poll`, but additionally reports the kind of the context node the error originated
+    /// from. Nodes that forward the state of their parent should forward this call as well.
+    fn poll_detail(&mut self) -> Poll<(), ErrorDetail> {
+        let source_kind = self.kind();
+        self.poll()
+            .map_err(|error| ErrorDetail { error, source_kind })
+    }
+
+    /// Like `poll_detail`, but through a shared reference (see `Context::poll_ref`). Nodes with
+    /// own cancelation or deadline state must implement this using interior mutability and must
+    /// support being polled by multiple tasks. The default forwards to the parent, or never
+    /// resolves for root nodes.
+    fn poll_detail_ref(&self) -> Poll<(), ErrorDetail> {
+        match self.parent() {
+            Some(parent) => parent.0.poll_detail_ref(),
+            None => Ok(Async::NotReady),
+        }
+    }
 }
 
-#[derive(Debug, PartialEq)]
+/// The reason a context is done.
+///
+/// The enum is marked `#[non_exhaustive]`, so that new variants can be added without breaking
+/// downstream code. When upgrading, `match`es listing all variants need to add a wildcard arm:
+///
+/// ```
+/// use ctx::ContextError;
+///
+/// fn is_timeout(err: ContextError) -> bool {
+///     match err {
+///         ContextError::DeadlineExceeded => true,
+///         ContextError::Canceled => false,
+///         _ => false,
+///     }
+/// }
+///
+/// assert!(is_timeout(ContextError::DeadlineExceeded));
+/// ```
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[non_exhaustive]
 pub enum ContextError {
     Canceled,
     DeadlineExceeded,
     DeadlineTooLong,
+    WorkExhausted,
 }
 
 impl fmt::Display for ContextError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "ContextError: {}", self.description())
+        write!(f, "ContextError: {}", self.as_str())
     }
 }
 
-impl Error for ContextError {
-    fn description(&self) -> &str {
+impl ContextError {
+    fn as_str(&self) -> &'static str {
         match *self {
             ContextError::Canceled => "context has been canceled",
             ContextError::DeadlineExceeded => "deadline has been exceeded",
             ContextError::DeadlineTooLong => "requested deadline too long",
+            ContextError::WorkExhausted => "work limit has been exhausted",
         }
     }
+
+    /// Returns a message describing the error that is suitable for clients of an API, e.g. in
+    /// the body of an error response. Contrary to the developer-facing `Display` output, it does
+    /// not mention internals like the context.
+    pub fn client_message(&self) -> &'static str {
+        match *self {
+            ContextError::Canceled => "the request was canceled",
+            ContextError::DeadlineExceeded => "the request timed out",
+            ContextError::DeadlineTooLong => "the request timeout is too long",
+            ContextError::WorkExhausted => "the request exceeded its work limit",
+        }
+    }
+}
+
+impl Error for ContextError {
+    fn description(&self) -> &str {
+        self.as_str()
+    }
+}
+
+/// The error returned by `Context::require_value` if the context has no value of the requested
+/// type.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct MissingValue {
+    pub type_name: &'static str,
+}
+
+impl fmt::Display for MissingValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected context value of type `{}`", self.type_name)
+    }
+}
+
+impl Error for MissingValue {}
+
+/// Maps `Canceled` to `ErrorKind::Interrupted`, `DeadlineExceeded` to `ErrorKind::TimedOut`,
+/// `DeadlineTooLong` to `ErrorKind::InvalidInput` and `WorkExhausted` to `ErrorKind::Other`.
+impl From<ContextError> for io::Error {
+    fn from(err: ContextError) -> Self {
+        let kind = match err {
+            ContextError::Canceled => io::ErrorKind::Interrupted,
+            ContextError::DeadlineExceeded => io::ErrorKind::TimedOut,
+            ContextError::DeadlineTooLong => io::ErrorKind::InvalidInput,
+            ContextError::WorkExhausted => io::ErrorKind::Other,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+/// A `ContextError` together with the kind of the context node that produced it.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ErrorDetail {
+    pub error: ContextError,
+    pub source_kind: &'static str,
+}
+
+impl fmt::Display for ErrorDetail {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (at {} context)", self.error, self.source_kind)
+    }
 }
 
 mod background {
-    use {InnerContext, ContextError};
+    use std::time::Instant;
+    use {InnerContext, ContextError, BACKGROUND_KIND};
     use futures::{Future, Poll, Async};
 
     #[derive(Clone)]
-    pub struct Background {}
+    pub struct Background {
+        pub created_at: Instant,
+    }
 
-    impl InnerContext for Background {}
+    impl InnerContext for Background {
+        fn kind(&self) -> &'static str {
+            BACKGROUND_KIND
+        }
+
+        fn created_at(&self) -> Option<Instant> {
+            Some(self.created_at)
+        }
+    }
 
     impl Future for Background {
         type Item = ();
@@ -130,8 +1020,427 @@ mod background {
     }
 }
 
+/// The kind of bare background contexts (see `background` and `Context::is_background`).
+pub const BACKGROUND_KIND: &str = "background";
+
 /// Returns an empty Context. It is never canceled has neither a value nor a deadline. It is
 /// typically used as a top-level Context.
+///
+/// Its future never resolves and does not register for any notifications, since there is
+/// nothing that could ever resolve it. Waiting on a background context directly (e.g. via
+/// `wait()`) therefore blocks forever; in debug builds, a warning is printed to stderr when a
+/// background context is polled directly.
 pub fn background() -> Context {
-    Context::new(background::Background {})
+    root_at(Instant::now())
+}
+
+/// Same as `background`, but the returned context reports the given time as its creation time
+/// (see `Context::created_at`), e.g. to pin the clock in tests. Combined with
+/// `with_timeout_anchored`, this allows to create contexts with predictable deadlines.
+pub fn root_at(created: Instant) -> Context {
+    Context::new(background::Background {
+        created_at: created,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::any::TypeId;
+    use std::io;
+    use std::sync::atomic::Ordering;
+    use std::time::{Duration, Instant};
+    use std::thread;
+    use futures::{Async, Future};
+    use futures::future::{self, poll_fn};
+    use {background, map_deadline, root_at, strip_values, with_cancel, with_tag, with_timeout,
+         with_timeout_anchored, with_value, with_value_filter, ContextError, BACKGROUND_KIND};
+
+    #[test]
+    fn into_io_test() {
+        let (ctx, cancel) = with_cancel(background());
+        cancel();
+        assert_eq!(ctx.into_io().wait().unwrap_err().kind(), io::ErrorKind::Interrupted);
+
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(10));
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(ctx.into_io().wait().unwrap_err().kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn find_value_test() {
+        #[derive(Debug, PartialEq, Clone)]
+        struct Tag {
+            name: &'static str,
+            priority: u8,
+        }
+
+        let ctx = with_value(background(), Tag { name: "a", priority: 7 });
+        let ctx = with_value(ctx, Tag { name: "b", priority: 3 });
+        let (ctx, _) = with_cancel(ctx);
+        let ctx = with_value(ctx, Tag { name: "c", priority: 9 });
+        let ctx = with_value(ctx, Tag { name: "d", priority: 1 });
+
+        assert_eq!(ctx.find_value(|tag: &Tag| tag.priority > 5).unwrap().name, "c");
+        assert_eq!(ctx.find_value(|tag: &Tag| tag.priority == 3).unwrap().name, "b");
+        assert_eq!(ctx.find_value(|tag: &Tag| tag.priority > 10), None);
+    }
+
+    #[test]
+    fn value_ref_after_combinator_test() {
+        let ctx = with_value(background(), String::from("a"));
+        let ctx = with_value(ctx, 42);
+        let (mut ctx, cancel) = with_cancel(ctx);
+
+        // polling through a combinator borrowing the context leaves its values intact
+        cancel();
+        let result = (&mut ctx).select2(future::empty::<(), ContextError>()).wait();
+        assert!(result.is_err());
+        assert_eq!(ctx.value_ref::<String>().map(|s| s.as_str()), Some("a"));
+        assert_eq!(ctx.value_ref::<i32>(), Some(&42));
+
+        // values cloned before the context is consumed outlive it
+        let val: String = ctx.value().unwrap();
+        let result = ctx.select2(future::empty::<(), ContextError>()).wait();
+        assert!(result.is_err());
+        assert_eq!(val, "a");
+    }
+
+    #[test]
+    fn require_value_test() {
+        #[derive(Debug, PartialEq, Clone)]
+        struct UserId(u64);
+
+        let ctx = with_value(background(), UserId(42));
+        assert_eq!(ctx.require_value(), Ok(UserId(42)));
+
+        let err = background().require_value::<UserId>().unwrap_err();
+        assert!(err.to_string().contains("UserId"), "{}", err);
+    }
+
+    #[test]
+    fn ancestors_test() {
+        let ctx = with_value(background(), 1);
+        let (ctx, _) = with_cancel(ctx);
+        let kinds: Vec<_> = ctx.ancestors().map(|ctx| ctx.0.kind()).collect();
+        assert_eq!(kinds, vec!["with_cancel", "with_value", "background"]);
+    }
+
+    #[test]
+    fn downstream_timeout_test() {
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(100));
+
+        let timeout = ctx.downstream_timeout(Duration::from_millis(20)).unwrap();
+        assert!(timeout <= Duration::from_millis(80));
+        assert!(timeout > Duration::from_millis(70));
+        assert_eq!(ctx.downstream_timeout(Duration::from_millis(200)), None);
+        assert_eq!(background().downstream_timeout(Duration::from_millis(20)), None);
+    }
+
+    #[test]
+    fn timeout_bucket_test() {
+        let buckets = [
+            Duration::from_millis(50),
+            Duration::from_millis(100),
+            Duration::from_secs(1),
+        ];
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(100));
+        let ctx = with_value(ctx, 42);
+        assert_eq!(ctx.timeout_bucket(&buckets), Some(1));
+
+        let (ctx, _) = with_timeout(ctx, Duration::from_secs(10));
+        assert_eq!(ctx.timeout_bucket(&buckets), Some(1));
+
+        let (ctx, _) = with_timeout(background(), Duration::from_secs(10));
+        assert_eq!(ctx.timeout_bucket(&buckets), None);
+        assert_eq!(background().timeout_bucket(&buckets), None);
+    }
+
+    #[test]
+    fn deadline_is_about_test() {
+        let tolerance = Duration::from_millis(50);
+        assert!(!background().deadline_is_about(Duration::ZERO, tolerance));
+
+        let (ctx, _) = with_timeout(background(), Duration::from_secs(1));
+        assert!(ctx.deadline_is_about(Duration::from_secs(1), tolerance));
+        assert!(ctx.deadline_is_about(Duration::from_millis(980), tolerance));
+        assert!(!ctx.deadline_is_about(Duration::from_millis(900), tolerance));
+        assert!(!ctx.deadline_is_about(Duration::from_millis(1100), tolerance));
+    }
+
+    #[test]
+    fn deadline_source_test() {
+        assert_eq!(background().deadline_source(), None);
+
+        let (ctx, _) = with_timeout(background(), Duration::from_secs(10));
+        let (ctx, _) = map_deadline(ctx, |when| when.map(|when| when - Duration::from_secs(5)));
+        let (ctx, _) = with_timeout(ctx, Duration::from_secs(20));
+        let ctx = with_value(ctx, 42);
+        assert_eq!(ctx.deadline_source(), Some("with_mapped_deadline"));
+
+        let (ctx, _) = with_timeout(ctx, Duration::from_secs(1));
+        assert_eq!(ctx.deadline_source(), Some("with_deadline"));
+    }
+
+    #[test]
+    fn deadline_remaining_or_err_test() {
+        assert_eq!(background().deadline_remaining_or_err(), Ok(None));
+
+        let (ctx, _) = with_timeout(background(), Duration::from_secs(10));
+        let remaining = ctx.deadline_remaining_or_err().unwrap().unwrap();
+        assert!(remaining > Duration::from_secs(9));
+
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(10));
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(ctx.deadline_remaining_or_err(), Err(ContextError::DeadlineExceeded));
+    }
+
+    #[test]
+    fn until_fire_test() {
+        let (ctx, cancel) = with_cancel(background());
+        assert_eq!(ctx.until_fire(), None);
+        cancel();
+        assert_eq!(ctx.until_fire(), Some(Duration::ZERO));
+
+        let (ctx, cancel) = with_timeout(background(), Duration::from_secs(10));
+        assert!(ctx.until_fire().unwrap() > Duration::from_secs(9));
+        cancel();
+        assert_eq!(ctx.until_fire(), Some(Duration::ZERO));
+
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(10));
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(ctx.until_fire(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn terminal_reason_test() {
+        let (ctx, cancel) = with_cancel(background());
+        let ctx = with_value(ctx, 42);
+        assert_eq!(ctx.terminal_reason(), None);
+        cancel();
+        assert_eq!(ctx.terminal_reason(), Some(ContextError::Canceled));
+
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(10));
+        assert_eq!(ctx.terminal_reason(), None);
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(ctx.terminal_reason(), Some(ContextError::DeadlineExceeded));
+    }
+
+    #[test]
+    fn elapsed_test() {
+        let root = background();
+        let created_at = root.created_at().unwrap();
+        thread::sleep(Duration::from_millis(20));
+
+        let (ctx, _) = with_cancel(root);
+        let ctx = with_value(ctx, 42);
+        assert_eq!(ctx.created_at(), Some(created_at));
+        let elapsed = ctx.elapsed();
+        assert!(elapsed >= Duration::from_millis(20));
+
+        thread::sleep(Duration::from_millis(10));
+        assert!(ctx.elapsed() >= elapsed + Duration::from_millis(10));
+    }
+
+    #[test]
+    fn poll_ref_test() {
+        let (ctx, cancel) = with_cancel(background());
+        let ctx = with_value(ctx, 42);
+        let (a, b) = (&ctx, &ctx);
+
+        let result = poll_fn(|| Ok::<_, ()>(Async::Ready((a.poll_ref(), b.poll_ref()))))
+            .wait()
+            .unwrap();
+        assert_eq!(result, (Ok(Async::NotReady), Ok(Async::NotReady)));
+        assert_eq!(a.value(), Some(42));
+
+        let waiter = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            cancel();
+        });
+        assert_eq!(poll_fn(|| a.poll_ref()).wait(), Err(ContextError::Canceled));
+        assert_eq!(poll_fn(|| b.poll_ref()).wait(), Err(ContextError::Canceled));
+        waiter.join().unwrap();
+
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(50));
+        let ctx = with_value(ctx, 42);
+        assert_eq!(poll_fn(|| ctx.poll_ref()).wait(), Err(ContextError::DeadlineExceeded));
+    }
+
+    #[test]
+    fn cancel_flag_test() {
+        let (ctx, cancel) = with_cancel(background());
+        let flag = ctx.cancel_flag();
+        let workers: Vec<_> = (0..8)
+            .map(|_| {
+                let flag = flag.clone();
+                thread::spawn(move || {
+                    let started = Instant::now();
+                    while !flag.load(Ordering::SeqCst) {
+                        assert!(started.elapsed() < Duration::from_secs(5));
+                        thread::yield_now();
+                    }
+                })
+            })
+            .collect();
+        thread::sleep(Duration::from_millis(20));
+        cancel();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(50));
+        let flag = ctx.cancel_flag();
+        assert!(!flag.load(Ordering::SeqCst));
+        thread::sleep(Duration::from_millis(100));
+        assert!(flag.load(Ordering::SeqCst));
+        assert!(ctx.cancel_flag().load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn clone_test() {
+        let (ctx, cancel) = with_cancel(background());
+        let ctx = with_value(ctx, 42);
+        let (ctx, _) = with_timeout(ctx, Duration::from_secs(10));
+        let clone = ctx.clone();
+        assert_eq!(clone.value(), Some(42));
+        assert_eq!(clone.deadline(), ctx.deadline());
+
+        let waiter = thread::spawn(move || clone.wait());
+        thread::sleep(Duration::from_millis(50));
+        cancel();
+
+        assert_eq!(waiter.join().unwrap(), Err(ContextError::Canceled));
+        assert_eq!(ctx.wait(), Err(ContextError::Canceled));
+    }
+
+    #[test]
+    fn downgrade_test() {
+        let (ctx, cancel) = with_cancel(background());
+        let ctx = with_value(ctx, 42);
+        let weak = ctx.downgrade();
+
+        let upgraded = weak.upgrade().unwrap();
+        assert_eq!(upgraded.value(), Some(42));
+        drop(upgraded);
+
+        // polling still works while weak handles exist
+        cancel();
+        assert_eq!(poll_fn(|| ctx.poll_ref()).wait(), Err(ContextError::Canceled));
+        let (child, _) = with_cancel(ctx);
+        assert!(weak.upgrade().is_some());
+        assert_eq!(child.wait(), Err(ContextError::Canceled));
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn explain_test() {
+        let (ctx, cancel) = with_timeout(background(), Duration::from_secs(10));
+        let ctx = with_value(ctx, 42);
+        let ctx = with_value(ctx, String::from("user"));
+
+        let explained = ctx.explain();
+        assert!(explained.starts_with("deadline: 9."), "{}", explained);
+        assert!(explained.contains("done: no"), "{}", explained);
+        assert!(explained.contains("depth: 4"), "{}", explained);
+        assert!(explained.ends_with("values: [alloc::string::String, i32]"), "{}", explained);
+
+        let shadowed = with_value(ctx.clone(), 1);
+        assert!(shadowed.explain().ends_with("values: [i32, alloc::string::String]"));
+        let filtered = with_value_filter(ctx.clone(), &[TypeId::of::<i32>()]);
+        assert!(filtered.explain().ends_with("values: [i32]"));
+        let (stripped, _) = strip_values(ctx.clone());
+        assert!(stripped.explain().ends_with("values: []"));
+
+        cancel();
+        assert!(ctx.explain().contains("done: context has been canceled"));
+        assert_eq!(
+            background().explain(),
+            "deadline: no deadline, done: no, depth: 0, values: []"
+        );
+    }
+
+    #[test]
+    fn next_wakeup_test() {
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(100));
+        assert!(ctx.next_wakeup().is_some());
+        assert_eq!(ctx.next_wakeup(), ctx.deadline());
+
+        let (ctx, _) = with_cancel(background());
+        assert_eq!(ctx.next_wakeup(), None);
+    }
+
+    #[test]
+    fn client_message_test() {
+        assert_eq!(ContextError::Canceled.client_message(), "the request was canceled");
+        assert_eq!(ContextError::DeadlineExceeded.client_message(), "the request timed out");
+        assert_eq!(
+            ContextError::DeadlineTooLong.client_message(),
+            "the request timeout is too long"
+        );
+        assert_eq!(
+            ContextError::WorkExhausted.client_message(),
+            "the request exceeded its work limit"
+        );
+        assert_ne!(
+            ContextError::DeadlineExceeded.client_message(),
+            ContextError::DeadlineExceeded.to_string()
+        );
+    }
+
+    #[test]
+    fn remaining_at_test() {
+        let start = Instant::now();
+        let (ctx, _) = with_timeout_anchored(root_at(start), Duration::from_secs(10), Some(start));
+        let at = |secs| ctx.remaining_at(start + Duration::from_secs(secs));
+        assert_eq!(at(0), Some(Duration::from_secs(10)));
+        assert_eq!(at(3), Some(Duration::from_secs(7)));
+        assert_eq!(at(10), Some(Duration::ZERO));
+        assert_eq!(at(60), Some(Duration::ZERO));
+
+        assert_eq!(background().remaining_at(start), None);
+    }
+
+    #[test]
+    fn is_background_test() {
+        assert!(background().is_background());
+        assert!(!with_value(background(), 42).is_background());
+        assert!(!with_cancel(background()).0.is_background());
+        assert!(!with_timeout(background(), Duration::from_secs(1)).0.is_background());
+        assert!(!with_tag(root_at(Instant::now()), "tagged").is_background());
+        assert_eq!(background().0.kind(), BACKGROUND_KIND);
+    }
+
+    #[test]
+    fn depth_test() {
+        assert_eq!(background().depth(), 0);
+        assert_eq!(background().chain_len(), 1);
+
+        let ctx = with_value(background(), 1);
+        let (ctx, _) = with_cancel(ctx);
+        let ctx = with_value(ctx, 2);
+        assert_eq!(ctx.depth(), 3);
+        assert_eq!(ctx.chain_len(), 4);
+    }
+
+    #[test]
+    fn into_parts_test() {
+        let (parent, cancel) = with_cancel(background());
+        let (ctx, _) = with_timeout(parent, Duration::from_millis(100));
+
+        let (timeout, canceled) = ctx.into_parts();
+        let timeout = timeout.unwrap();
+        assert!(timeout <= Duration::from_millis(100));
+        assert!(timeout > Duration::from_millis(90));
+        assert!(!canceled.load(Ordering::SeqCst));
+
+        cancel();
+        assert!(canceled.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn io_error_from_test() {
+        let err = io::Error::from(ContextError::DeadlineTooLong);
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(err.to_string(), ContextError::DeadlineTooLong.to_string());
+    }
 }
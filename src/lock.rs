@@ -0,0 +1,92 @@
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard,
+                RwLockWriteGuard};
+use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static PROPAGATE: AtomicBool = AtomicBool::new(false);
+
+/// Controls how the locks guarding the internal state of contexts (e.g. of `Signal`s, timers and
+/// lazily computed values) are handled once they are poisoned, i.e. once a thread panicked while
+/// holding them.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LockPolicy {
+    /// Ignore the poisoning and continue with the guarded state (the default). The state is
+    /// always consistent, since it is only ever updated atomically.
+    Recover,
+    /// Panic when encountering a poisoned lock.
+    Propagate,
+}
+
+/// Sets the policy used for all contexts when encountering a poisoned lock. Defaults to
+/// `LockPolicy::Recover`.
+pub fn set_lock_policy(policy: LockPolicy) {
+    PROPAGATE.store(policy == LockPolicy::Propagate, Ordering::SeqCst);
+}
+
+/// Locks the given mutex, handling poisoning according to the current `LockPolicy`.
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    recover(mutex.lock())
+}
+
+/// Same as `lock`, but acquires shared read access to the given `RwLock`.
+pub(crate) fn read<T>(rw_lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    recover(rw_lock.read())
+}
+
+/// Same as `lock`, but acquires exclusive write access to the given `RwLock`.
+pub(crate) fn write<T>(rw_lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    recover(rw_lock.write())
+}
+
+/// Waits on the given condition variable, handling poisoning like `lock`.
+pub(crate) fn wait<'a, T>(cond: &Condvar, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+    recover(cond.wait(guard))
+}
+
+/// Same as `wait`, but waits for at most the given duration.
+pub(crate) fn wait_timeout<'a, T>(
+    cond: &Condvar,
+    guard: MutexGuard<'a, T>,
+    timeout: Duration,
+) -> MutexGuard<'a, T> {
+    recover(cond.wait_timeout(guard, timeout).map(|(guard, _)| guard).map_err(|err| {
+        PoisonError::new(err.into_inner().0)
+    }))
+}
+
+fn recover<G>(result: Result<G, PoisonError<G>>) -> G {
+    match result {
+        Ok(guard) => guard,
+        Err(_) if PROPAGATE.load(Ordering::SeqCst) => panic!("ctx: lock poisoned"),
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::panic::{self, AssertUnwindSafe};
+    use lock::{set_lock_policy, LockPolicy};
+    use Signal;
+
+    /// Returns a signal whose locks are poisoned by registering outside of a task context.
+    fn poisoned_signal() -> Signal {
+        let signal = Signal::new();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| signal.register()));
+        assert!(result.is_err());
+        signal
+    }
+
+    // both policies are tested within the same test, since the policy is global
+    #[test]
+    fn lock_policy_test() {
+        let signal = poisoned_signal();
+        signal.fire();
+        assert!(signal.is_fired());
+
+        set_lock_policy(LockPolicy::Propagate);
+        let signal = poisoned_signal();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| signal.fire()));
+        set_lock_policy(LockPolicy::Recover);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,55 @@
+use slog::{Discard, Logger};
+use {Context, WithValue};
+
+/// Returns a copy of parent with the given logger associated to it, e.g. a logger pre-populated
+/// with request-scoped key-value pairs. Requires the `slog` feature.
+pub fn with_logger(parent: Context, logger: Logger) -> Context {
+    Context::new(WithValue::new(parent, logger))
+}
+
+impl Context {
+    /// Returns the closest logger associated to the context via `with_logger`, or a logger
+    /// discarding all records if there is none. Requires the `slog` feature.
+    pub fn logger(&self) -> Logger {
+        self.value::<Logger>()
+            .unwrap_or_else(|| Logger::root(Discard, o!()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+    use slog::{Drain, Logger, Never, OwnedKVList, Record};
+    use logger::with_logger;
+    use lock::lock;
+    use {background, with_cancel};
+
+    struct Collect(Arc<Mutex<Vec<String>>>);
+
+    impl Drain for Collect {
+        type Ok = ();
+        type Err = Never;
+
+        fn log(&self, record: &Record, _: &OwnedKVList) -> Result<(), Never> {
+            lock(&self.0).push(record.msg().to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn logger_test() {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let root = Logger::root(Collect(records.clone()), o!());
+
+        let ctx = with_logger(background(), root.new(o!("request_id" => 1)));
+        let (ctx, _) = with_cancel(ctx);
+        info!(ctx.logger(), "hello");
+
+        assert_eq!(*lock(&records), vec![String::from("hello")]);
+    }
+
+    #[test]
+    fn default_logger_test() {
+        info!(background().logger(), "discarded");
+    }
+}
@@ -0,0 +1,287 @@
+use std::time::Instant;
+use {Context, ContextError, ErrorDetail, InnerContext};
+use futures::{Async, Future, Poll};
+
+pub struct MergedValues {
+    primary: Context,
+    secondary: Context,
+}
+
+impl InnerContext for MergedValues {
+    fn parent(&self) -> Option<&Context> {
+        Some(&self.primary)
+    }
+
+    fn secondary(&self) -> Option<&Context> {
+        Some(&self.secondary)
+    }
+
+    fn kind(&self) -> &'static str {
+        "merge_values"
+    }
+
+    fn poll_detail(&mut self) -> Poll<(), ErrorDetail> {
+        self.primary.poll_detail()
+    }
+}
+
+impl Future for MergedValues {
+    type Item = ();
+    type Error = ContextError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.primary.poll_inner()
+    }
+}
+
+/// Returns a context that resolves values from both given contexts, e.g. to compose a request
+/// context with the values of an ambient application context. Values are looked up in `primary`
+/// first, and only then in `secondary`.
+///
+/// Only values are merged: the deadline and the cancelation of the returned context follow
+/// `primary` alone, i.e. canceling `secondary` does not affect it.
+///
+/// # Examples
+///
+/// ```
+/// use ctx::{merge_values, with_value, background};
+///
+/// let request = with_value(background(), "request-id");
+/// let app = with_value(background(), 42);
+///
+/// let ctx = merge_values(request, app);
+/// assert_eq!(ctx.value(), Some("request-id"));
+/// assert_eq!(ctx.value(), Some(42));
+/// ```
+pub fn merge_values(primary: Context, secondary: Context) -> Context {
+    Context::new(MergedValues { primary, secondary })
+}
+
+pub struct MergedContexts {
+    /// All merged contexts; the first one is the parent of the node.
+    parents: Vec<Context>,
+    /// The remaining contexts chained via `merge_values`, to resolve their values in order.
+    secondary: Option<Context>,
+}
+
+impl InnerContext for MergedContexts {
+    fn parent(&self) -> Option<&Context> {
+        self.parents.first()
+    }
+
+    fn merged_parents(&self) -> &[Context] {
+        &self.parents[1..]
+    }
+
+    fn secondary(&self) -> Option<&Context> {
+        self.secondary.as_ref()
+    }
+
+    fn kind(&self) -> &'static str {
+        "merge_all"
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        // the deadline of the first context is inherited as the parent
+        self.parents[1..].iter().filter_map(Context::deadline).min()
+    }
+
+    fn poll_deadline(&self) -> Poll<(), ContextError> {
+        for parent in &self.parents {
+            if let Async::Ready(()) = parent.0.poll_deadline()? {
+                return Ok(Async::Ready(()));
+            }
+        }
+        Ok(Async::NotReady)
+    }
+
+    fn poll_detail(&mut self) -> Poll<(), ErrorDetail> {
+        for parent in &mut self.parents {
+            if let Async::Ready(()) = parent.poll_detail()? {
+                return Ok(Async::Ready(()));
+            }
+        }
+        Ok(Async::NotReady)
+    }
+
+    fn poll_detail_ref(&self) -> Poll<(), ErrorDetail> {
+        for parent in &self.parents {
+            if let Async::Ready(()) = parent.0.poll_detail_ref()? {
+                return Ok(Async::Ready(()));
+            }
+        }
+        Ok(Async::NotReady)
+    }
+}
+
+impl Future for MergedContexts {
+    type Item = ();
+    type Error = ContextError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.poll_detail().map_err(|detail| detail.error)
+    }
+}
+
+/// Returns a context merging all of the given contexts, e.g. for fan-in coordinators with
+/// multiple upstream contexts. The returned context's future resolves once any of the contexts
+/// resolves (with its error), its deadline is the earliest deadline among them, and values are
+/// looked up in the given contexts in order.
+///
+/// Each poll of the returned context polls every merged context once. Cancelation of any of the
+/// merged contexts is also observed without polling the returned context, e.g. via
+/// `Context::done` or `Context::terminal_reason`.
+///
+/// # Panics
+///
+/// Panics if `ctxs` is empty.
+///
+/// # Examples
+///
+/// ```
+/// extern crate ctx;
+/// extern crate futures;
+///
+/// use ctx::{merge_all, with_cancel, with_value, background, ContextError};
+/// use futures::Future;
+///
+/// fn main() {
+///     let (a, _) = with_cancel(with_value(background(), 42));
+///     let (b, cancel) = with_cancel(with_value(background(), "b"));
+///
+///     let ctx = merge_all(vec![a, b]);
+///     assert_eq!(ctx.value(), Some(42));
+///     assert_eq!(ctx.value(), Some("b"));
+///
+///     cancel();
+///     assert_eq!(ctx.wait(), Err(ContextError::Canceled));
+/// }
+/// ```
+pub fn merge_all(ctxs: Vec<Context>) -> Context {
+    assert!(!ctxs.is_empty(), "merge_all requires at least one context");
+    let secondary = ctxs[1..]
+        .iter()
+        .rev()
+        .cloned()
+        .reduce(|secondary, primary| merge_values(primary, secondary));
+    Context::new(MergedContexts {
+        parents: ctxs,
+        secondary,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+    use futures::{future, stream, Async, Future, Stream};
+    use merge::{merge_all, merge_values};
+    use {background, with_cancel, with_tag, with_timeout, with_value, Context, ContextError};
+
+    #[test]
+    fn merge_values_test() {
+        let primary = with_value(background(), 1);
+        let primary = with_value(primary, "primary");
+        let secondary = with_value(background(), 2);
+        let secondary = with_value(secondary, 1.5);
+
+        let ctx = merge_values(primary, secondary);
+        let ctx = with_tag(ctx, "merged");
+        assert_eq!(ctx.value(), Some(1));
+        assert_eq!(ctx.value(), Some("primary"));
+        assert_eq!(ctx.value(), Some(1.5));
+        assert_eq!(ctx.find_value(|n: &i32| *n > 1), Some(2));
+        assert_eq!(ctx.value::<u8>(), None);
+    }
+
+    #[test]
+    fn merge_values_tags_test() {
+        let primary = with_tag(background(), "a");
+        let secondary = with_tag(background(), "b");
+        let ctx = with_tag(merge_values(primary, secondary), "c");
+        assert_eq!(ctx.tags(), vec!["c", "a", "b"]);
+    }
+
+    #[test]
+    fn merge_values_cancel_test() {
+        let (secondary, cancel) = with_cancel(background());
+        let (primary, _) = with_timeout(background(), Duration::from_secs(10));
+        let deadline = primary.deadline();
+
+        let mut ctx = merge_values(primary, secondary);
+        cancel();
+        assert_eq!(ctx.deadline(), deadline);
+        let result = future::poll_fn(|| Ok::<_, ()>(Async::Ready(ctx.poll()))).wait();
+        assert_eq!(result, Ok(Ok(Async::NotReady)));
+
+        let (primary, cancel) = with_cancel(background());
+        let ctx = merge_values(primary, background());
+        cancel();
+        assert_eq!(ctx.wait().unwrap_err(), ContextError::Canceled);
+    }
+
+    #[test]
+    fn merge_all_test() {
+        let (a, _) = with_timeout(with_value(background(), 1), Duration::from_secs(10));
+        let (b, _) = with_timeout(with_value(background(), 2), Duration::from_secs(5));
+        let c = with_value(with_value(background(), 3), "c");
+        let deadline = b.deadline();
+
+        let ctx = merge_all(vec![a, b, c]);
+        assert_eq!(ctx.deadline(), deadline);
+        assert_eq!(ctx.value(), Some(1));
+        assert_eq!(ctx.value(), Some("c"));
+        assert_eq!(ctx.find_value(|n: &i32| *n > 1), Some(2));
+    }
+
+    #[test]
+    fn merge_all_cancel_test() {
+        let (parents, mut cancels): (Vec<Context>, Vec<_>) =
+            (0..5).map(|_| with_cancel(background())).unzip();
+        let mut ctx = merge_all(parents);
+
+        let result = future::poll_fn(|| Ok::<_, ()>(Async::Ready(ctx.poll()))).wait();
+        assert_eq!(result, Ok(Ok(Async::NotReady)));
+
+        cancels.remove(3)();
+        assert_eq!(ctx.wait().unwrap_err(), ContextError::Canceled);
+    }
+
+    #[test]
+    fn merge_all_observers_test() {
+        let (parents, mut cancels): (Vec<Context>, Vec<_>) =
+            (0..3).map(|_| with_cancel(background())).unzip();
+        let ctx = merge_all(parents);
+
+        let done = ctx.done();
+        let shared_done = ctx.shared_done();
+        let flag = ctx.cancel_flag();
+        let heartbeat = ctx.heartbeat(Duration::from_secs(10));
+        let forwarded = ctx.cancel_stream(stream::empty::<(), ContextError>());
+        let (_tx, rx) = ctx.bounded::<()>(1);
+        assert_eq!(ctx.terminal_reason(), None);
+
+        cancels.remove(2)();
+        assert_eq!(done.wait().unwrap_err(), ContextError::Canceled);
+        assert_eq!(*shared_done.wait().unwrap_err(), ContextError::Canceled);
+        assert!(flag.load(Ordering::SeqCst));
+        assert_eq!(heartbeat.collect().wait().unwrap_err(), ContextError::Canceled);
+        assert_eq!(forwarded.collect().wait().unwrap_err(), ContextError::Canceled);
+        assert_eq!(rx.collect().wait().unwrap_err(), ContextError::Canceled);
+        assert_eq!(ctx.terminal_reason(), Some(ContextError::Canceled));
+        assert!(ctx.is_canceled());
+        assert_eq!(ctx.cancel_source(), Some("with_cancel"));
+        assert_eq!(ctx.until_fire(), Some(Duration::ZERO));
+        assert!(ctx.explain().contains("done: context has been canceled"));
+    }
+
+    #[test]
+    fn merge_all_poll_deadline_test() {
+        let (a, _) = with_timeout(background(), Duration::from_secs(10));
+        let (b, _) = with_timeout(background(), Duration::from_millis(50));
+        let mut ctx = merge_all(vec![a, b]);
+
+        let result = future::poll_fn(|| ctx.poll_deadline()).wait();
+        assert_eq!(result, Err(ContextError::DeadlineExceeded));
+    }
+}
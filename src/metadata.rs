@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use {Context, WithValue};
+
+/// A map of metadata entries associated via `with_metadata`. Each node keeps only the entries
+/// it has been created with; they are merged across the chain on lookup.
+struct Metadata(HashMap<String, String>);
+
+/// Returns a copy of parent with the given string key/value pairs (e.g. HTTP or gRPC headers)
+/// associated to it. Contrary to values, metadata maps are merged across the chain, with entries
+/// of inner contexts overriding those of outer ones.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashMap;
+/// use ctx::{with_metadata, background};
+///
+/// let mut map = HashMap::new();
+/// map.insert("x-request-id".to_string(), "abc".to_string());
+/// let ctx = with_metadata(background(), map);
+/// assert_eq!(ctx.metadata_get("x-request-id"), Some("abc".to_string()));
+/// ```
+pub fn with_metadata(parent: Context, map: HashMap<String, String>) -> Context {
+    Context::new(WithValue::new(parent, Metadata(map)))
+}
+
+impl Context {
+    /// Returns the metadata entry for the given key, as associated via `with_metadata` to the
+    /// closest context containing it.
+    pub fn metadata_get(&self, key: &str) -> Option<String> {
+        self.values::<Metadata>()
+            .filter_map(|m| m.0.get(key))
+            .next()
+            .cloned()
+    }
+
+    /// Returns all metadata entries associated to the context and its ancestors via
+    /// `with_metadata`, with entries of inner contexts overriding those of outer ones.
+    pub fn metadata(&self) -> HashMap<String, String> {
+        let mut merged = HashMap::new();
+        for m in self.values::<Metadata>() {
+            for (key, val) in &m.0 {
+                merged.entry(key.clone()).or_insert_with(|| val.clone());
+            }
+        }
+        merged
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use metadata::with_metadata;
+    use {background, with_cancel};
+
+    fn map(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|&(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn metadata_test() {
+        let ctx = with_metadata(background(), map(&[("a", "1"), ("b", "2")]));
+        let (ctx, _) = with_cancel(ctx);
+        let ctx = with_metadata(ctx, map(&[("b", "3"), ("c", "4")]));
+
+        assert_eq!(ctx.metadata(), map(&[("a", "1"), ("b", "3"), ("c", "4")]));
+        assert_eq!(ctx.metadata_get("a"), Some("1".to_string()));
+        assert_eq!(ctx.metadata_get("b"), Some("3".to_string()));
+        assert_eq!(ctx.metadata_get("d"), None);
+        assert!(background().metadata().is_empty());
+    }
+}
@@ -0,0 +1,126 @@
+use std::any::{Any, TypeId};
+use {Context, ContextError, ErrorDetail, InnerContext};
+use futures::{Future, Poll};
+
+pub struct WithNamedValue<V>
+where
+    V: Any + Send + Sync,
+{
+    parent: Context,
+    name: String,
+    val: V,
+}
+
+impl<V> InnerContext for WithNamedValue<V>
+where
+    V: Any + Send + Sync,
+{
+    fn named_value(&self, name: &str) -> Option<&dyn Any> {
+        if self.name == name {
+            Some(&self.val as &dyn Any)
+        } else {
+            None
+        }
+    }
+
+    fn parent(&self) -> Option<&Context> {
+        Some(&self.parent)
+    }
+
+    fn kind(&self) -> &'static str {
+        "with_named_value"
+    }
+
+    fn poll_detail(&mut self) -> Poll<(), ErrorDetail> {
+        self.parent.poll_detail()
+    }
+}
+
+impl<V> Future for WithNamedValue<V>
+where
+    V: Any + Send + Sync,
+{
+    type Item = ();
+    type Error = ContextError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.parent.poll_inner()
+    }
+}
+
+/// Returns a copy of parent with the given value associated to it under the given name. Contrary
+/// to `with_value`, the value is looked up by its name (see `Context::named_value`) instead of by
+/// its type, e.g. for dynamically loaded plugins, which cannot rely on `TypeId`s being identical
+/// across compilation units.
+///
+/// Named values are not visible to `Context::value`.
+///
+/// # Examples
+///
+/// ```
+/// use ctx::{with_named_value, background};
+///
+/// let ctx = with_named_value(background(), "db_pool", 42);
+/// assert_eq!(ctx.named_value("db_pool"), Some(42));
+/// assert_eq!(ctx.named_value::<i32>("cache"), None);
+/// ```
+pub fn with_named_value<V>(parent: Context, name: &str, val: V) -> Context
+where
+    V: Any + Send + Sync,
+{
+    // named values are not looked up by type, thus they cannot shadow values of `with_value`
+    #[cfg(feature = "strict-values")]
+    ::strict::check_declared::<V>();
+
+    Context::new(WithNamedValue {
+        parent,
+        name: name.to_string(),
+        val,
+    })
+}
+
+impl Context {
+    /// Returns the closest value associated to the context under the given name via
+    /// `with_named_value`, or `None` if there is no value with that name, or if the closest one
+    /// is not of type `T`.
+    pub fn named_value<T>(&self, name: &str) -> Option<T>
+    where
+        T: Any + Clone,
+    {
+        self.value_nodes(TypeId::of::<T>())
+            .find_map(|ctx| ctx.0.named_value(name))
+            .and_then(|val_any| val_any.downcast_ref::<T>())
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use named::with_named_value;
+    use {background, strip_values, with_cancel, with_value};
+
+    #[test]
+    fn named_value_test() {
+        let ctx = with_named_value(background(), "db_pool", String::from("pool"));
+        let ctx = with_named_value(ctx, "retries", 3);
+        let (ctx, _) = with_cancel(ctx);
+        let ctx = with_value(ctx, 42);
+
+        assert_eq!(ctx.named_value("db_pool"), Some(String::from("pool")));
+        assert_eq!(ctx.named_value("retries"), Some(3));
+        assert_eq!(ctx.named_value::<i32>("cache"), None);
+        assert_eq!(ctx.named_value::<u8>("retries"), None);
+        assert_eq!(ctx.value::<String>(), None);
+    }
+
+    #[test]
+    fn shadowed_named_value_test() {
+        let ctx = with_named_value(background(), "id", 1);
+        let ctx = with_named_value(ctx, "id", "one");
+        assert_eq!(ctx.named_value("id"), Some("one"));
+        assert_eq!(ctx.named_value::<i32>("id"), None);
+
+        let (ctx, _) = strip_values(ctx);
+        assert_eq!(ctx.named_value::<&str>("id"), None);
+    }
+}
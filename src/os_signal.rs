@@ -0,0 +1,44 @@
+use std::io;
+use std::thread;
+use {background, with_cancel, Context};
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+
+/// Returns a top-level context, which is canceled once the process receives `SIGINT` or
+/// `SIGTERM`. This is the canonical root context of a server that should shut down gracefully.
+///
+/// The signal handlers are registered before this function returns and are handled on a
+/// dedicated thread. Registering a handler replaces the default behavior of the signals, i.e.
+/// the process is no longer terminated by them; it is up to the application to exit once the
+/// context is canceled.
+///
+/// Only available on Unix platforms with the `signal` feature enabled.
+pub fn cancel_on_signal() -> io::Result<Context> {
+    let mut signals = Signals::new([SIGINT, SIGTERM])?;
+    let (ctx, cancel) = with_cancel(background());
+    thread::Builder::new()
+        .name("ctx-signal".to_string())
+        .spawn(move || {
+            if signals.forever().next().is_some() {
+                cancel();
+            }
+        })?;
+    Ok(ctx)
+}
+
+#[cfg(test)]
+mod test {
+    use signal_hook::consts::SIGTERM;
+    use signal_hook::low_level::raise;
+    use os_signal::cancel_on_signal;
+    use ContextError;
+    use futures::Future;
+
+    #[test]
+    fn cancel_on_signal_test() {
+        let ctx = cancel_on_signal().unwrap();
+        raise(SIGTERM).unwrap();
+
+        assert_eq!(ctx.wait().unwrap_err(), ContextError::Canceled);
+    }
+}
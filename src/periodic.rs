@@ -0,0 +1,63 @@
+use std::time::{Duration, Instant};
+use {Context, WeakContext};
+use timer::schedule;
+
+impl Context {
+    /// Calls `f` every `interval` until the context is done (or all handles to it have been
+    /// dropped), e.g. to send keepalive pings on behalf of a request. The first call happens once
+    /// the first interval elapsed.
+    ///
+    /// The calls are scheduled on the timer's shared scheduler thread, i.e. they happen even if
+    /// nobody polls the context, and `f` should return quickly.
+    pub fn spawn_periodic<F>(&self, interval: Duration, f: F)
+    where
+        F: 'static + Fn() + Send,
+    {
+        schedule_tick(self.downgrade(), Instant::now() + interval, interval, f);
+    }
+}
+
+fn schedule_tick<F>(ctx: WeakContext, when: Instant, interval: Duration, f: F)
+where
+    F: 'static + Fn() + Send,
+{
+    schedule(when, move || {
+        if let Some(ctx) = ctx.upgrade() {
+            if ctx.terminal_reason().is_none() {
+                f();
+                // scheduled relative to the previous tick, so that the ticks do not drift
+                schedule_tick(ctx.downgrade(), when + interval, interval, f);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+    use {background, with_cancel};
+
+    #[test]
+    fn spawn_periodic_test() {
+        let ticks = Arc::new(AtomicUsize::new(0));
+        let (ctx, cancel) = with_cancel(background());
+        {
+            let ticks = ticks.clone();
+            ctx.spawn_periodic(Duration::from_millis(100), move || {
+                ticks.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+
+        thread::sleep(Duration::from_millis(450));
+        cancel();
+        thread::sleep(Duration::from_millis(50));
+        let count = ticks.load(Ordering::SeqCst);
+        assert!((2..=5).contains(&count), "unexpected number of ticks: {}", count);
+
+        thread::sleep(Duration::from_millis(300));
+        assert_eq!(ticks.load(Ordering::SeqCst), count);
+    }
+}
@@ -0,0 +1,53 @@
+use {Context, WithValue};
+
+/// The priority of the work done on behalf of a context, e.g. for load shedding to decide which
+/// requests to drop under pressure. Priorities are ordered from `Low` to `Critical`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Critical,
+}
+
+/// Returns a copy of parent with the given priority associated to it.
+///
+/// # Examples
+///
+/// ```
+/// use ctx::{with_priority, background, Priority};
+///
+/// let ctx = with_priority(background(), Priority::High);
+/// assert!(ctx.priority() > Priority::Normal);
+/// ```
+pub fn with_priority(parent: Context, priority: Priority) -> Context {
+    Context::new(WithValue::new(parent, priority))
+}
+
+impl Context {
+    /// Returns the priority associated to the context via `with_priority`, or `Priority::Normal`
+    /// if there is none.
+    pub fn priority(&self) -> Priority {
+        self.value().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use priority::{with_priority, Priority};
+    use {background, with_cancel, with_value};
+
+    #[test]
+    fn priority_test() {
+        assert_eq!(background().priority(), Priority::Normal);
+
+        let ctx = with_priority(background(), Priority::Low);
+        let (ctx, _) = with_cancel(ctx);
+        let ctx = with_value(ctx, 42);
+        assert_eq!(ctx.priority(), Priority::Low);
+
+        let ctx = with_priority(ctx, Priority::Critical);
+        assert_eq!(ctx.priority(), Priority::Critical);
+    }
+}
@@ -0,0 +1,81 @@
+use std::env;
+use std::process::Command;
+use std::time::Duration;
+use {background, with_timeout, Context};
+
+/// The name of the environment variable used to pass the remaining time of a context (in
+/// milliseconds) to child processes.
+pub const DEADLINE_ENV_VAR: &str = "CTX_DEADLINE_MS";
+
+impl Context {
+    /// Passes the remaining time until the context's deadline to the child process spawned by
+    /// `cmd`, using the `CTX_DEADLINE_MS` environment variable. The child can restore the
+    /// deadline using `Context::from_env`. If the context has no deadline, the variable is
+    /// removed from the child's environment.
+    pub fn apply_to_command(&self, cmd: &mut Command) {
+        match self.deadline_remaining() {
+            Some(remaining) => {
+                cmd.env(DEADLINE_ENV_VAR, remaining.as_millis().to_string());
+            }
+            None => {
+                cmd.env_remove(DEADLINE_ENV_VAR);
+            }
+        }
+    }
+
+    /// Creates a top-level context with the deadline passed by the parent process via
+    /// `Context::apply_to_command`. Returns a background context if the `CTX_DEADLINE_MS`
+    /// environment variable is not set or invalid.
+    pub fn from_env() -> Context {
+        let timeout = env::var(DEADLINE_ENV_VAR)
+            .ok()
+            .and_then(|ms| ms.parse().ok())
+            .map(Duration::from_millis);
+        match timeout {
+            Some(timeout) => with_timeout(background(), timeout).0,
+            None => background(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::ffi::OsStr;
+    use std::process::Command;
+    use std::time::Duration;
+    use process::DEADLINE_ENV_VAR;
+    use {background, with_timeout, Context};
+
+    fn deadline_env(cmd: &Command) -> Option<Option<&OsStr>> {
+        cmd.get_envs()
+            .find(|&(key, _)| key == DEADLINE_ENV_VAR)
+            .map(|(_, val)| val)
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let (ctx, _) = with_timeout(background(), Duration::from_secs(10));
+        let mut cmd = Command::new("true");
+        ctx.apply_to_command(&mut cmd);
+
+        let ms = deadline_env(&cmd).unwrap().unwrap();
+        let ms: u64 = ms.to_str().unwrap().parse().unwrap();
+        assert!(ms > 9_000 && ms <= 10_000);
+
+        env::set_var(DEADLINE_ENV_VAR, ms.to_string());
+        let child = Context::from_env();
+        env::remove_var(DEADLINE_ENV_VAR);
+
+        let remaining = child.deadline_remaining().unwrap();
+        assert!(remaining > Duration::from_secs(9));
+        assert!(remaining <= Duration::from_millis(ms));
+    }
+
+    #[test]
+    fn no_deadline_test() {
+        let mut cmd = Command::new("true");
+        background().apply_to_command(&mut cmd);
+        assert_eq!(deadline_env(&cmd), Some(None));
+    }
+}
@@ -0,0 +1,119 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use {Context, Signal, WithCancel};
+use lock::lock;
+use timer::schedule;
+
+/// The handle returned by `with_revocable_cancel`, which cancels the context unless the
+/// cancelation is revoked within a grace window.
+#[derive(Clone)]
+pub struct RevocableCancel {
+    signal: Signal,
+    grace: Duration,
+    /// The generation of the latest cancelation and whether it is still pending. The generation
+    /// is incremented on each `cancel`, so that a revoked cancelation cannot fire once a new one
+    /// is pending.
+    pending: Arc<Mutex<(u64, bool)>>,
+}
+
+impl RevocableCancel {
+    /// Requests the cancelation of the context, which happens once the grace window elapsed,
+    /// unless `revoke` is called in the meantime. Calling it while a cancelation is already
+    /// pending does not restart the grace window.
+    pub fn cancel(&self) {
+        let generation = {
+            let mut pending = lock(&self.pending);
+            if pending.1 || self.signal.is_fired() {
+                return;
+            }
+            pending.0 += 1;
+            pending.1 = true;
+            pending.0
+        };
+
+        let signal = self.signal.clone();
+        let pending = self.pending.clone();
+        schedule(Instant::now() + self.grace, move || {
+            // keep the lock while firing, so that a concurrent revoke either happens before (and
+            // prevents the cancelation) or after (and reports it as too late)
+            let pending = lock(&pending);
+            if *pending == (generation, true) {
+                signal.fire();
+            }
+        });
+    }
+
+    /// Revokes a pending cancelation. Returns whether there was a pending cancelation to revoke,
+    /// i.e. `false` if none has been requested or if it is too late, because the grace window
+    /// already elapsed.
+    pub fn revoke(&self) -> bool {
+        let mut pending = lock(&self.pending);
+        if !pending.1 || self.signal.is_fired() {
+            return false;
+        }
+        pending.1 = false;
+        true
+    }
+}
+
+/// Returns a copy of parent, which is canceled once the returned handle's `cancel` is called,
+/// unless the cancelation is revoked via `revoke` within the given grace window, e.g. for
+/// speculative cancelation that might get rescinded. This is experimental.
+///
+/// Concurrency subtleties to be aware of:
+///
+/// - the context stays live during the grace window; observers only notice the cancelation
+///   once the window elapsed,
+/// - `revoke` racing with the end of the grace window either prevents the cancelation or
+///   returns `false`; use its result to find out which one happened.
+pub fn with_revocable_cancel(parent: Context, grace: Duration) -> (Context, RevocableCancel) {
+    let signal = Signal::new();
+    let cancel = RevocableCancel {
+        signal: signal.clone(),
+        grace,
+        pending: Arc::new(Mutex::new((0, false))),
+    };
+    (Context::new(WithCancel::new(parent, signal, false)), cancel)
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+    use std::time::Duration;
+    use futures::Future;
+    use revocable::with_revocable_cancel;
+    use {background, ContextError};
+
+    #[test]
+    fn revoke_test() {
+        let (ctx, cancel) = with_revocable_cancel(background(), Duration::from_millis(100));
+        assert!(!cancel.revoke());
+
+        cancel.cancel();
+        thread::sleep(Duration::from_millis(20));
+        assert!(cancel.revoke());
+
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(ctx.terminal_reason(), None);
+    }
+
+    #[test]
+    fn cancel_test() {
+        let (ctx, cancel) = with_revocable_cancel(background(), Duration::from_millis(100));
+        cancel.cancel();
+        assert_eq!(ctx.terminal_reason(), None);
+
+        assert_eq!(ctx.wait(), Err(ContextError::Canceled));
+        assert!(!cancel.revoke());
+    }
+
+    #[test]
+    fn cancel_after_revoke_test() {
+        let (ctx, cancel) = with_revocable_cancel(background(), Duration::from_millis(100));
+        cancel.cancel();
+        assert!(cancel.revoke());
+        cancel.cancel();
+
+        assert_eq!(ctx.wait(), Err(ContextError::Canceled));
+    }
+}
@@ -0,0 +1,192 @@
+use std::sync::{Arc, Mutex, Weak};
+use std::sync::atomic::{AtomicBool, Ordering};
+use futures::task::{self, AtomicTask, Task};
+use ContextError;
+use lock::lock;
+
+/// A one-shot, thread-safe flag used by cancelable context nodes. Once fired, all tasks that
+/// registered interest in the signal are notified and all registered callbacks are called.
+#[derive(Clone, Default)]
+pub struct Signal {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    fired: AtomicBool,
+    error: Mutex<Option<ContextError>>,
+    source: Mutex<Option<&'static str>>,
+    tasks: Mutex<Vec<Task>>,
+    callbacks: Mutex<Vec<Box<dyn FnOnce() + Send>>>,
+    /// Flags set once the signal fires. Only weak references are kept, so that dropped flags do
+    /// not accumulate (see `set_on_fire`).
+    flags: Mutex<Vec<Weak<AtomicBool>>>,
+    /// The tasks of the futures waiting for the signal (see `add_waiter`). Only weak references
+    /// are kept, so that waiters that have been dropped do not accumulate.
+    waiters: Mutex<Vec<Weak<AtomicTask>>>,
+}
+
+impl Signal {
+    pub fn new() -> Self {
+        Signal::default()
+    }
+
+    /// Fires the signal with `ContextError::Canceled` and notifies all registered tasks. Firing an
+    /// already fired signal is a no-op.
+    pub fn fire(&self) {
+        self.fire_with(ContextError::Canceled)
+    }
+
+    /// Fires the signal with the given error and notifies all registered tasks. Firing an already
+    /// fired signal is a no-op.
+    pub fn fire_with(&self, error: ContextError) {
+        self.fire_inner(error, None)
+    }
+
+    /// Same as `fire`, but additionally records the given source (e.g. the name of the component
+    /// that canceled the context), which can be retrieved via `source`.
+    pub fn fire_from(&self, source: &'static str) {
+        self.fire_inner(ContextError::Canceled, Some(source))
+    }
+
+    fn fire_inner(&self, error: ContextError, source: Option<&'static str>) {
+        {
+            let mut current = lock(&self.inner.error);
+            if current.is_some() {
+                return;
+            }
+            *current = Some(error);
+            *lock(&self.inner.source) = source;
+        }
+
+        self.inner.fired.store(true, Ordering::SeqCst);
+        let tasks = ::std::mem::take(&mut *lock(&self.inner.tasks));
+        for task in tasks {
+            task.notify();
+        }
+        let callbacks = ::std::mem::take(&mut *lock(&self.inner.callbacks));
+        for callback in callbacks {
+            callback();
+        }
+        let flags = ::std::mem::take(&mut *lock(&self.inner.flags));
+        for flag in flags.iter().filter_map(Weak::upgrade) {
+            flag.store(true, Ordering::SeqCst);
+        }
+        let waiters = ::std::mem::take(&mut *lock(&self.inner.waiters));
+        for task in waiters.iter().filter_map(Weak::upgrade) {
+            task.notify();
+        }
+    }
+
+    /// Returns whether the signal has been fired.
+    pub fn is_fired(&self) -> bool {
+        self.inner.fired.load(Ordering::SeqCst)
+    }
+
+    /// Returns the error the signal has been fired with, or `None` if it has not been fired yet.
+    pub fn error(&self) -> Option<ContextError> {
+        if self.is_fired() {
+            *lock(&self.inner.error)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the source the signal has been fired from (see `fire_from`), or `None` if it has
+    /// not been fired yet or has been fired without a source.
+    pub fn source(&self) -> Option<&'static str> {
+        if self.is_fired() {
+            *lock(&self.inner.source)
+        } else {
+            None
+        }
+    }
+
+    /// Registers the current task to be notified once the signal fires. Must be called from
+    /// within a task context.
+    ///
+    /// The task is kept until the signal fires; futures waiting for a long-lived signal should use
+    /// their own `AtomicTask` instead (see `add_waiter`).
+    pub fn register(&self) {
+        let mut tasks = lock(&self.inner.tasks);
+        if !tasks.iter().any(|task| task.will_notify_current()) {
+            tasks.push(task::current());
+        }
+    }
+
+    /// Calls `f` once the signal fires, or immediately if it already fired.
+    pub fn on_fire<F>(&self, f: F)
+    where
+        F: 'static + FnOnce() + Send,
+    {
+        let mut callbacks = lock(&self.inner.callbacks);
+        if self.is_fired() {
+            drop(callbacks);
+            f();
+        } else {
+            callbacks.push(Box::new(f));
+        }
+    }
+
+    /// Sets `flag` once the signal fires, or immediately if it already fired. Unlike `on_fire`,
+    /// the registration is released once the flag is dropped.
+    pub(crate) fn set_on_fire(&self, flag: &Arc<AtomicBool>) {
+        let mut flags = lock(&self.inner.flags);
+        if self.is_fired() {
+            flag.store(true, Ordering::SeqCst);
+        } else {
+            flags.retain(|flag| flag.strong_count() > 0);
+            flags.push(Arc::downgrade(flag));
+        }
+    }
+
+    /// Notifies the task registered with `task` once the signal fires, or immediately if it
+    /// already fired. The registration is released once `task` is dropped.
+    pub(crate) fn add_waiter(&self, task: &Arc<AtomicTask>) {
+        let mut waiters = lock(&self.inner.waiters);
+        if self.is_fired() {
+            task.notify();
+        } else {
+            waiters.retain(|task| task.strong_count() > 0);
+            waiters.push(Arc::downgrade(task));
+        }
+    }
+
+    #[cfg(test)]
+    fn flag_count(&self) -> usize {
+        lock(&self.inner.flags).len()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn waiter_count(&self) -> usize {
+        lock(&self.inner.waiters).len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use Signal;
+
+    #[test]
+    fn set_on_fire_test() {
+        let signal = Signal::new();
+        let flag = Arc::new(AtomicBool::new(false));
+        signal.set_on_fire(&flag);
+
+        // dropped flags are released on the next registration
+        for _ in 0..10 {
+            signal.set_on_fire(&Arc::new(AtomicBool::new(false)));
+        }
+        assert_eq!(signal.flag_count(), 2);
+
+        signal.fire();
+        assert!(flag.load(Ordering::SeqCst));
+        assert_eq!(signal.flag_count(), 0);
+
+        let late = Arc::new(AtomicBool::new(false));
+        signal.set_on_fire(&late);
+        assert!(late.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,112 @@
+use std::any::{Any, TypeId};
+#[cfg(debug_assertions)]
+use std::any::type_name;
+use std::collections::HashSet;
+use std::sync::{OnceLock, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use lock::{read, write};
+
+static DECLARED: OnceLock<RwLock<HashSet<TypeId>>> = OnceLock::new();
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+fn declared() -> &'static RwLock<HashSet<TypeId>> {
+    DECLARED.get_or_init(Default::default)
+}
+
+/// Declares `V` as a type that is meant to be used as a context value. Use the
+/// `declare_context_value!` macro instead of calling this function directly.
+///
+/// The declaration is global to the process.
+pub fn declare_value<V>()
+where
+    V: Any,
+{
+    write(declared()).insert(TypeId::of::<V>());
+}
+
+/// Enables or disables strict mode for the whole process. In strict mode, `with_value` (as well
+/// as `with_lazy_value`, `with_named_value` and `Context::push_value`) panics (in debug builds
+/// only) when it is called with a value whose type has not been declared via
+/// `declare_context_value!`. This helps to enforce that context values are only used for
+/// request-scoped data, and not for passing optional parameters.
+pub fn set_strict_values(enabled: bool) {
+    STRICT.store(enabled, Ordering::SeqCst);
+}
+
+/// Panics if strict mode is enabled and `V` has not been declared as a context value.
+pub(crate) fn check_declared<V>()
+where
+    V: Any,
+{
+    #[cfg(debug_assertions)]
+    {
+        if STRICT.load(Ordering::SeqCst) {
+            assert_declared::<V>();
+        }
+    }
+}
+
+#[cfg(debug_assertions)]
+fn assert_declared<V>()
+where
+    V: Any,
+{
+    if !read(declared()).contains(&TypeId::of::<V>()) {
+        panic!(
+            "ctx: context value of type `{}`, which has not been declared via \
+             `declare_context_value!`",
+            type_name::<V>()
+        );
+    }
+}
+
+/// Declares types as context values, which is required for them to be used with `with_value`
+/// once strict mode is enabled via `set_strict_values`.
+///
+/// # Example
+///
+/// ```
+/// #[macro_use]
+/// extern crate ctx;
+///
+/// use ctx::{with_value, background, set_strict_values};
+///
+/// #[derive(Clone)]
+/// struct RequestId(u64);
+///
+/// fn main() {
+///     declare_context_value!(RequestId);
+///     set_strict_values(true);
+///
+///     let ctx = with_value(background(), RequestId(1));
+///     assert_eq!(ctx.value::<RequestId>().map(|id| id.0), Some(1));
+/// }
+/// ```
+#[macro_export]
+macro_rules! declare_context_value {
+    ($($val:ty),+ $(,)*) => {
+        $($crate::declare_value::<$val>();)+
+    };
+}
+
+#[cfg(all(test, debug_assertions))]
+mod test {
+    use std::panic;
+    use strict::assert_declared;
+    use {background, with_value};
+
+    struct Declared;
+    struct Undeclared;
+
+    // strict mode itself is global and would affect other tests running concurrently, which is
+    // why the check is tested directly here, and strict mode in `tests/strict_values.rs`
+    #[test]
+    fn strict_values_test() {
+        declare_context_value!(Declared);
+        assert_declared::<Declared>();
+        assert!(with_value(background(), Declared).value_ref::<Declared>().is_some());
+
+        let result = panic::catch_unwind(assert_declared::<Undeclared>);
+        assert!(result.is_err());
+    }
+}
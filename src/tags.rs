@@ -0,0 +1,64 @@
+use {Context, WithValue};
+
+/// A tag associated via `with_tag`. Wrapping it keeps `Context::value::<&'static str>` from
+/// returning tags, and tags from picking up plain string values.
+#[derive(Clone, Copy)]
+struct Tag(&'static str);
+
+/// Returns a copy of parent with the given tag (e.g. a route name or a feature flag) associated
+/// to it. Contrary to values, tags do not shadow each other; all of them can be retrieved using
+/// `Context::tags`.
+///
+/// # Examples
+///
+/// ```
+/// use ctx::{with_tag, background};
+///
+/// let ctx = with_tag(background(), "api");
+/// let ctx = with_tag(ctx, "users#show");
+/// assert_eq!(ctx.tags(), vec!["users#show", "api"]);
+/// ```
+pub fn with_tag(parent: Context, tag: &'static str) -> Context {
+    Context::new(WithValue::new(parent, Tag(tag)))
+}
+
+impl Context {
+    /// Returns all tags associated to the context and its ancestors via `with_tag`, starting
+    /// with the closest one.
+    pub fn tags(&self) -> Vec<&'static str> {
+        self.values::<Tag>().map(|tag| tag.0).collect()
+    }
+
+    /// Returns whether the given tag is associated to the context or any of its ancestors.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.values::<Tag>().any(|t| t.0 == tag)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tags::with_tag;
+    use {background, with_cancel, with_value};
+
+    #[test]
+    fn tags_test() {
+        let ctx = with_tag(background(), "a");
+        let (ctx, _) = with_cancel(ctx);
+        let ctx = with_tag(ctx, "b");
+        let ctx = with_tag(ctx, "c");
+
+        assert_eq!(ctx.tags(), vec!["c", "b", "a"]);
+        assert!(ctx.has_tag("b"));
+        assert!(!ctx.has_tag("d"));
+        assert!(background().tags().is_empty());
+    }
+
+    #[test]
+    fn no_collision_test() {
+        let ctx = with_value(background(), "value");
+        let ctx = with_tag(ctx, "tag");
+
+        assert_eq!(ctx.value(), Some("value"));
+        assert_eq!(ctx.tags(), vec!["tag"]);
+    }
+}
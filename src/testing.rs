@@ -0,0 +1,99 @@
+use std::time::Duration;
+use futures::Future;
+use futures::future::{poll_fn, Either};
+use {Context, ContextError};
+use timer::timer;
+
+/// Verifies that cancelation propagates through the whole chain of the given context, e.g. to
+/// test that a middleware stack is wired correctly. Cancels the outermost cancelable ancestor of
+/// the context (the one closest to its top-level context) and asserts that polling the context
+/// itself fails with `ContextError::Canceled` within one second.
+///
+/// Note that this cancels the chain, so the context cannot be used for anything else afterwards.
+///
+/// # Panics
+///
+/// Panics if the chain has no cancelable ancestor, or if the context does not observe the
+/// cancelation in time.
+///
+/// # Example
+///
+/// ```
+/// use ctx::{assert_propagates_cancellation, with_cancel, with_value, background};
+///
+/// let (root, _) = with_cancel(background());
+/// let ctx = with_value(root, 42);
+/// assert_propagates_cancellation(&ctx);
+/// ```
+pub fn assert_propagates_cancellation(ctx: &Context) {
+    let signal = ctx
+        .ancestors()
+        .filter_map(|ctx| ctx.0.signal())
+        .last()
+        .expect("context chain has no cancelable ancestor to cancel");
+    signal.fire();
+
+    let timeout = timer().sleep(Duration::from_secs(1));
+    match poll_fn(|| ctx.poll_ref()).select2(timeout).wait() {
+        Err(Either::A((ContextError::Canceled, _))) => {}
+        Err(Either::A((err, _))) => {
+            panic!("context failed with {:?} instead of observing the cancelation", err)
+        }
+        _ => panic!("context did not observe the cancelation of its outermost ancestor"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::{Async, Future, Poll};
+    use testing::assert_propagates_cancellation;
+    use {background, with_cancel, with_timeout, with_value};
+    use {Context, ContextError, ErrorDetail, InnerContext};
+    use std::time::Duration;
+
+    /// A node that does not forward the state of its parent.
+    struct Detached(Context);
+
+    impl InnerContext for Detached {
+        fn parent(&self) -> Option<&Context> {
+            Some(&self.0)
+        }
+
+        fn poll_detail_ref(&self) -> Poll<(), ErrorDetail> {
+            Ok(Async::NotReady)
+        }
+    }
+
+    impl Future for Detached {
+        type Item = ();
+        type Error = ContextError;
+
+        fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+            Ok(Async::NotReady)
+        }
+    }
+
+    #[test]
+    fn propagates_test() {
+        let (root, _) = with_cancel(background());
+        let ctx = with_value(root, 42);
+        let (ctx, _) = with_timeout(ctx, Duration::from_secs(10));
+        let (ctx, _) = with_cancel(ctx);
+        assert_propagates_cancellation(&ctx);
+    }
+
+    #[test]
+    #[should_panic(expected = "did not observe the cancelation")]
+    fn detached_test() {
+        let (root, _) = with_cancel(background());
+        let ctx = Context::new(Detached(root));
+        let ctx = with_value(ctx, 42);
+        assert_propagates_cancellation(&ctx);
+    }
+
+    #[test]
+    #[should_panic(expected = "no cancelable ancestor")]
+    fn not_cancelable_test() {
+        assert_propagates_cancellation(&with_value(background(), 42));
+    }
+}
@@ -0,0 +1,242 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use futures::{Async, Future, Poll};
+use futures::future::Shared;
+use futures::task::{self, Task};
+use tokio_timer::{self, Sleep, Timer, TimerError};
+use lock::{lock, wait, wait_timeout};
+
+static TIMER: Mutex<Option<Timer>> = Mutex::new(None);
+
+/// The sleeps shared by all coalesced deadlines, per deadline.
+static BUCKETS: OnceLock<Mutex<HashMap<Instant, Shared<Sleep>>>> = OnceLock::new();
+
+/// The instant coalesced deadlines are aligned to.
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+/// Returns a handle to the timer shared by all contexts. The timer thread is started lazily on
+/// first use (and after `shutdown_timer`).
+pub(crate) fn timer() -> Timer {
+    lock(&TIMER)
+        .get_or_insert_with(|| tokio_timer::wheel().thread_name("ctx-timer").build())
+        .clone()
+}
+
+/// Releases the timer shared by all contexts, so that its background thread can stop and does
+/// not keep the process alive, e.g. during a graceful shutdown.
+///
+/// The thread stops once all deadlines created before the shutdown have been dropped. Creating a
+/// new deadline afterwards (e.g. via `with_timeout`) lazily starts a new timer thread.
+pub fn shutdown_timer() {
+    lock(&TIMER).take();
+}
+
+/// A callback scheduled via `schedule`.
+struct Job {
+    when: Instant,
+    /// Keeps jobs scheduled for the same instant in the order they have been scheduled.
+    seq: u64,
+    run: Box<dyn FnOnce() + Send>,
+}
+
+impl PartialEq for Job {
+    fn eq(&self, other: &Self) -> bool {
+        (self.when, self.seq) == (other.when, other.seq)
+    }
+}
+
+impl Eq for Job {}
+
+impl PartialOrd for Job {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Job {
+    // reversed, so that the earliest job is at the top of the (max-)heap
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.when, other.seq).cmp(&(self.when, self.seq))
+    }
+}
+
+#[derive(Default)]
+struct Scheduler {
+    jobs: Mutex<(BinaryHeap<Job>, u64)>,
+    wakeup: Condvar,
+}
+
+static SCHEDULER: OnceLock<Scheduler> = OnceLock::new();
+
+fn scheduler() -> &'static Scheduler {
+    SCHEDULER.get_or_init(|| {
+        thread::Builder::new()
+            .name("ctx-scheduler".to_string())
+            .spawn(|| scheduler().run())
+            .expect("failed to spawn the ctx-scheduler thread");
+        Scheduler::default()
+    })
+}
+
+impl Scheduler {
+    fn run(&self) {
+        loop {
+            let job = {
+                let mut jobs = lock(&self.jobs);
+                loop {
+                    let now = Instant::now();
+                    match jobs.0.peek().map(|job| job.when) {
+                        Some(when) if when <= now => break jobs.0.pop().unwrap(),
+                        Some(when) => jobs = wait_timeout(&self.wakeup, jobs, when - now),
+                        None => jobs = wait(&self.wakeup, jobs),
+                    }
+                }
+            };
+            // a panicking callback must not take down the callbacks of all other contexts; the
+            // panic is still reported by the panic hook
+            let _ = panic::catch_unwind(AssertUnwindSafe(job.run));
+        }
+    }
+}
+
+/// Calls `f` at the given instant on a single thread shared by all contexts, e.g. for callbacks
+/// that must run even if nobody polls the context. Callbacks are expected to return quickly, since
+/// they delay all callbacks scheduled after them.
+///
+/// # Panics
+///
+/// Panics if the scheduler thread cannot be spawned on first use.
+pub(crate) fn schedule<F>(when: Instant, f: F)
+where
+    F: 'static + FnOnce() + Send,
+{
+    let scheduler = scheduler();
+    let mut jobs = lock(&scheduler.jobs);
+    jobs.1 += 1;
+    let seq = jobs.1;
+    jobs.0.push(Job {
+        when,
+        seq,
+        run: Box::new(f),
+    });
+    scheduler.wakeup.notify_one();
+}
+
+/// A deadline that can be polled through a shared reference by multiple tasks. Since each handle
+/// to a shared `Sleep` only notifies the task that polled it last, a separate handle is kept per
+/// polling task. The handles are released once the deadline expired (at the latest after the
+/// maximum timeout of the timer), instead of being kept for the lifetime of the deadline.
+pub(crate) struct SharedDeadline {
+    sleep: Shared<Sleep>,
+    /// `None` once the deadline expired.
+    handles: Mutex<Option<Vec<Handle>>>,
+}
+
+/// A handle to the shared sleep, together with the task it notifies.
+type Handle = (Task, Shared<Sleep>);
+
+impl SharedDeadline {
+    pub(crate) fn new(when: Instant) -> Self {
+        let timeout = when.saturating_duration_since(Instant::now());
+        SharedDeadline::from_sleep(timer().sleep(timeout).shared())
+    }
+
+    /// Creates a deadline that expires at `when` rounded up to the next multiple of `granularity`
+    /// (aligned to a process-wide epoch). All coalesced deadlines expiring at the same instant
+    /// share a single timer entry. Returns the deadline together with the rounded instant.
+    pub(crate) fn coalesced(when: Instant, granularity: Duration) -> (Self, Instant) {
+        let epoch = *EPOCH.get_or_init(Instant::now);
+        let granularity = granularity.as_nanos().max(1);
+        let since = when.saturating_duration_since(epoch).as_nanos();
+        let buckets = since.div_ceil(granularity);
+        let rounded = epoch + Duration::from_nanos((buckets * granularity) as u64);
+
+        let now = Instant::now();
+        let mut shared = lock(BUCKETS.get_or_init(Default::default));
+        shared.retain(|bucket, _| *bucket > now);
+        let sleep = shared
+            .entry(rounded)
+            .or_insert_with(|| timer().sleep(rounded.saturating_duration_since(now)).shared())
+            .clone();
+        (SharedDeadline::from_sleep(sleep), rounded)
+    }
+
+    fn from_sleep(sleep: Shared<Sleep>) -> Self {
+        SharedDeadline {
+            sleep,
+            handles: Mutex::new(Some(Vec::new())),
+        }
+    }
+
+    /// Notifies all tasks that polled the deadline, e.g. because it has been replaced.
+    pub(crate) fn notify(&self) {
+        for (task, _) in lock(&self.handles).iter().flatten() {
+            task.notify();
+        }
+    }
+
+    /// Polls the deadline on behalf of the current task. Must be called from within a task
+    /// context.
+    pub(crate) fn poll(&self) -> Poll<(), TimerError> {
+        let mut handles = lock(&self.handles);
+        let result = match handles.as_mut() {
+            // a completed sleep resolves immediately, without keeping a handle around
+            None => self.sleep.clone().poll(),
+            Some(handles) => {
+                let pos = match handles.iter().position(|(task, _)| task.will_notify_current()) {
+                    Some(pos) => pos,
+                    None => {
+                        handles.push((task::current(), self.sleep.clone()));
+                        handles.len() - 1
+                    }
+                };
+                handles[pos].1.poll()
+            }
+        };
+        match result {
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            result => {
+                *handles = None;
+                result.map(|_| Async::Ready(())).map_err(|err| (*err).clone())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc;
+    use std::time::{Duration, Instant};
+    use timer::{schedule, shutdown_timer};
+    use {background, with_timeout, ContextError};
+    use futures::Future;
+
+    #[test]
+    fn restart_test() {
+        let (before, _) = with_timeout(background(), Duration::from_millis(50));
+        shutdown_timer();
+        let (after, _) = with_timeout(background(), Duration::from_millis(50));
+
+        assert_eq!(before.wait().unwrap_err(), ContextError::DeadlineExceeded);
+        assert_eq!(after.wait().unwrap_err(), ContextError::DeadlineExceeded);
+    }
+
+    #[test]
+    fn schedule_test() {
+        let (tx, rx) = mpsc::channel();
+        let start = Instant::now();
+        for (i, delay) in [150, 50, 100, 50].iter().enumerate() {
+            let tx = tx.clone();
+            schedule(start + Duration::from_millis(*delay), move || tx.send(i).unwrap());
+        }
+        schedule(start, || panic!("must not stop the scheduler"));
+
+        let order: Vec<usize> = rx.iter().take(4).collect();
+        assert_eq!(order, vec![1, 3, 2, 0]);
+        assert!(start.elapsed() >= Duration::from_millis(150));
+    }
+}
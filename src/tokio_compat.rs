@@ -0,0 +1,25 @@
+use tokio::time::Instant;
+use Context;
+
+impl Context {
+    /// Returns the effective deadline of the context as a tokio `Instant`, e.g. to be passed to
+    /// `tokio::time::timeout_at`. Requires the `tokio` feature.
+    pub fn tokio_deadline(&self) -> Option<Instant> {
+        self.deadline().map(Instant::from_std)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use {background, with_timeout};
+
+    #[test]
+    fn tokio_deadline_test() {
+        assert_eq!(background().tokio_deadline(), None);
+
+        let (ctx, _) = with_timeout(background(), Duration::from_secs(10));
+        let deadline = ctx.tokio_deadline().unwrap();
+        assert_eq!(deadline.into_std(), ctx.deadline().unwrap());
+    }
+}
@@ -0,0 +1,203 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::ptr;
+use std::sync::{Arc, OnceLock, RwLock};
+use Context;
+use lock::{read, write};
+
+type Registry = RwLock<HashMap<(TypeId, TypeId), Arc<dyn Any + Send + Sync>>>;
+
+static REGISTRY: OnceLock<Registry> = OnceLock::new();
+
+/// Views a `&dyn Any` of a registered concrete type as `&T`.
+trait Cast<T: ?Sized>: Send + Sync {
+    fn cast<'a>(&self, val_any: &'a dyn Any) -> Option<&'a T>;
+}
+
+struct FnCast<T: ?Sized, C>(fn(&C) -> &T);
+
+impl<T, C> Cast<T> for FnCast<T, C>
+where
+    T: ?Sized + 'static,
+    C: Any,
+{
+    fn cast<'a>(&self, val_any: &'a dyn Any) -> Option<&'a T> {
+        val_any.downcast_ref::<C>().map(self.0)
+    }
+}
+
+struct Caster<T: ?Sized>(Box<dyn Cast<T>>);
+
+fn registry() -> &'static Registry {
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// Registers how context values of type `C` can be viewed as `T` (usually a trait object like
+/// `dyn MyTrait`), so that they can be retrieved using `Context::value_as::<T>()`. Use the
+/// `register_trait!` macro to avoid having to provide the conversion manually.
+///
+/// The registration is global to the process.
+pub fn register_trait<T, C>(cast: fn(&C) -> &T)
+where
+    T: ?Sized + 'static,
+    C: Any,
+{
+    let caster = Caster::<T>(Box::new(FnCast(cast)));
+    write(registry())
+        .insert((TypeId::of::<T>(), TypeId::of::<C>()), Arc::new(caster));
+}
+
+/// Registers how context values of a concrete type can be viewed as a trait object. See
+/// `register_trait` for details.
+///
+/// # Example
+///
+/// ```
+/// #[macro_use]
+/// extern crate ctx;
+///
+/// use ctx::{with_value, background};
+///
+/// trait Named {
+///     fn name(&self) -> String;
+/// }
+///
+/// struct User;
+///
+/// impl Named for User {
+///     fn name(&self) -> String {
+///         "user".to_string()
+///     }
+/// }
+///
+/// fn main() {
+///     register_trait!(dyn Named, User);
+///
+///     let ctx = with_value(background(), User);
+///     assert_eq!(ctx.value_as::<dyn Named>().unwrap().name(), "user");
+/// }
+/// ```
+#[macro_export]
+macro_rules! register_trait {
+    ($trait:ty, $concrete:ty) => {
+        $crate::register_trait::<$trait, $concrete>(|val| val)
+    };
+}
+
+impl Context {
+    /// Returns the closest value among the context and its ancestors that can be viewed as `T`,
+    /// as previously registered via `register_trait`. Values are looked up like `Context::value`
+    /// does, i.e. including lazy values and the values of secondary contexts (see
+    /// `merge_values`).
+    pub fn value_as<T>(&self) -> Option<&T>
+    where
+        T: ?Sized + 'static,
+    {
+        let casters: Vec<(TypeId, Arc<dyn Any + Send + Sync>)> = read(registry())
+            .iter()
+            .filter(|((trait_id, _), _)| *trait_id == TypeId::of::<T>())
+            .map(|((_, type_id), caster)| (*type_id, caster.clone()))
+            .collect();
+        // the closest visible node per registered type, the closest of which wins
+        let visible: Vec<(&Context, usize)> = casters
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (type_id, _))| {
+                self.value_nodes(*type_id)
+                    .find(|ctx| ctx.0.value_type() == Some(*type_id))
+                    .map(|ctx| (ctx, i))
+            })
+            .collect();
+        let (ctx, i) = self.lookup_nodes(None).find_map(|node| {
+            visible.iter().find(|(ctx, _)| ptr::eq(*ctx, node))
+        })?;
+        let (type_id, caster) = &casters[*i];
+        let caster = caster.downcast_ref::<Caster<T>>()?;
+        caster.0.cast(ctx.0.value_of(*type_id)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::any::TypeId;
+    use {background, merge_values, with_lazy_value, with_value, with_value_filter};
+
+    trait Greet {
+        fn greet(&self) -> String;
+    }
+
+    struct English;
+    struct German(&'static str);
+
+    impl Greet for English {
+        fn greet(&self) -> String {
+            "hello".to_string()
+        }
+    }
+
+    impl Greet for German {
+        fn greet(&self) -> String {
+            format!("hallo {}", self.0)
+        }
+    }
+
+    #[test]
+    fn value_as_test() {
+        register_trait!(dyn Greet, English);
+        register_trait!(dyn Greet, German);
+
+        let ctx = with_value(background(), German("welt"));
+        let ctx = with_value(ctx, 42);
+        assert_eq!(ctx.value_as::<dyn Greet>().unwrap().greet(), "hallo welt");
+
+        let ctx = with_value(ctx, English);
+        assert_eq!(ctx.value_as::<dyn Greet>().unwrap().greet(), "hello");
+    }
+
+    #[test]
+    fn unregistered_test() {
+        trait Unregistered {}
+        impl Unregistered for i32 {}
+
+        let ctx = with_value(background(), 42);
+        assert!(ctx.value_as::<dyn Unregistered>().is_none());
+    }
+
+    #[test]
+    fn filtered_test() {
+        register_trait!(dyn Greet, English);
+        register_trait!(dyn Greet, German);
+
+        let ctx = with_value(background(), English);
+        let ctx = with_value_filter(ctx, &[TypeId::of::<i32>()]);
+        assert!(ctx.value_as::<dyn Greet>().is_none());
+
+        // the filter only hides the values of its ancestors
+        let ctx = with_value(ctx, German("welt"));
+        let ctx = with_value_filter(ctx, &[TypeId::of::<German>()]);
+        assert_eq!(ctx.value_as::<dyn Greet>().unwrap().greet(), "hallo welt");
+    }
+
+    #[test]
+    fn lazy_test() {
+        register_trait!(dyn Greet, English);
+        register_trait!(dyn Greet, German);
+
+        let ctx = with_value(background(), English);
+        let ctx = with_lazy_value(ctx, || German("lazy"));
+        assert_eq!(ctx.value_as::<dyn Greet>().unwrap().greet(), "hallo lazy");
+    }
+
+    #[test]
+    fn merged_test() {
+        register_trait!(dyn Greet, English);
+        register_trait!(dyn Greet, German);
+
+        let ctx = merge_values(with_value(background(), 42), with_value(background(), English));
+        assert_eq!(ctx.value_as::<dyn Greet>().unwrap().greet(), "hello");
+
+        let primary = with_value(background(), German("primary"));
+        let ctx = merge_values(primary, with_value(background(), English));
+        assert_eq!(ctx.value_as::<dyn Greet>().unwrap().greet(), "hallo primary");
+    }
+}
@@ -0,0 +1,109 @@
+use std::any::Any;
+use std::ops::Deref;
+use {Context, WithValue};
+
+/// Overrides a value for the scope of the guard (see `Context::push_value`). Derefs to the
+/// context carrying the override; dropping the guard drops the override with it.
+#[must_use = "the value is popped once the guard is dropped"]
+pub struct ValueGuard {
+    ctx: Context,
+}
+
+impl Deref for ValueGuard {
+    type Target = Context;
+
+    fn deref(&self) -> &Context {
+        &self.ctx
+    }
+}
+
+impl Context {
+    /// Pushes the given value on top of the context, overriding values of the same type, until
+    /// the returned guard is dropped, e.g. down one branch of a recursive algorithm.
+    ///
+    /// The override is only visible through the guard (and contexts derived from it), not
+    /// through the context itself or other clones of it. Code down the branch thus has to be
+    /// handed the guard (which derefs to a `Context`) instead of the original context. There is
+    /// deliberately no interior-mutable value stack shared by all clones of a context: pushes to
+    /// it would leak into concurrent branches (on other threads or in other futures), and popped
+    /// values could not be freed while references to them handed out by `Context::value_ref`
+    /// are still alive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ctx::{with_value, background, Context};
+    ///
+    /// fn depth(ctx: &Context, n: u32) -> u32 {
+    ///     if n == 0 {
+    ///         return ctx.value().unwrap();
+    ///     }
+    ///     let ctx = ctx.push_value(n);
+    ///     depth(&ctx, n - 1)
+    /// }
+    ///
+    /// let ctx = with_value(background(), 0u32);
+    /// assert_eq!(depth(&ctx, 3), 1);
+    /// assert_eq!(ctx.value(), Some(0u32));
+    /// ```
+    pub fn push_value<T>(&self, val: T) -> ValueGuard
+    where
+        T: Any + Send + Sync,
+    {
+        // overriding is intended, thus only the strict-values check applies, but not the
+        // shadowing check of `with_value`
+        #[cfg(feature = "strict-values")]
+        ::strict::check_declared::<T>();
+
+        ValueGuard {
+            ctx: Context::new(WithValue::new(self.clone(), val)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+    use {background, with_cancel, with_value};
+
+    #[test]
+    fn push_value_test() {
+        let (ctx, _) = with_cancel(with_value(background(), "original"));
+        assert_eq!(ctx.value(), Some("original"));
+
+        {
+            let outer = ctx.push_value("outer");
+            let number = outer.push_value(42);
+            assert_eq!(number.value(), Some("outer"));
+            {
+                let inner = number.push_value("inner");
+                assert_eq!(inner.value(), Some("inner"));
+                assert_eq!(inner.value(), Some(42));
+            }
+            assert_eq!(number.value(), Some("outer"));
+        }
+
+        assert_eq!(ctx.value(), Some("original"));
+        assert_eq!(ctx.value::<i32>(), None);
+    }
+
+    #[test]
+    fn concurrent_push_value_test() {
+        let ctx = with_value(background(), 0);
+        let branches: Vec<_> = (1..5)
+            .map(|i| {
+                let ctx = ctx.clone();
+                thread::spawn(move || {
+                    let ctx = ctx.push_value(i);
+                    thread::yield_now();
+                    ctx.value::<i32>()
+                })
+            })
+            .collect();
+
+        for (i, branch) in (1..5).zip(branches) {
+            assert_eq!(branch.join().unwrap(), Some(i));
+        }
+        assert_eq!(ctx.value(), Some(0));
+    }
+}
@@ -1,17 +1,98 @@
-use std::sync::{Arc, Mutex};
-use {Context, InnerContext, ContextError};
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+use {Context, InnerContext, CancelFn, ContextError, Done, ErrorDetail, SharedDone, Signal};
 use futures::{Future, Poll, Async};
-use futures::task::{self, Task};
+use futures::task::AtomicTask;
 
 pub struct WithCancel {
     parent: Context,
-    canceled: Arc<Mutex<bool>>, // TODO: Arc necessary?
-    handle: Arc<Mutex<Option<Task>>>,
+    signal: Signal,
+    cancel_on_drop: bool,
+    completed_at: OnceLock<Instant>,
+    shared_done: OnceLock<SharedDone>,
+    /// The task that last polled the context, which is notified once the signal fires.
+    task: Arc<AtomicTask>,
+}
+
+impl WithCancel {
+    pub(crate) fn new(parent: Context, signal: Signal, cancel_on_drop: bool) -> Self {
+        let task = Arc::new(AtomicTask::new());
+        let notify = task.clone();
+        signal.on_fire(move || notify.notify());
+        WithCancel {
+            parent,
+            signal,
+            cancel_on_drop,
+            completed_at: OnceLock::new(),
+            shared_done: OnceLock::new(),
+            task,
+        }
+    }
 }
 
 impl InnerContext for WithCancel {
     fn parent(&self) -> Option<&Context> {
-        self.parent.0.parent()
+        Some(&self.parent)
+    }
+
+    fn signal(&self) -> Option<&Signal> {
+        Some(&self.signal)
+    }
+
+    fn kind(&self) -> &'static str {
+        "with_cancel"
+    }
+
+    fn completed_at(&self) -> Option<Instant> {
+        self.completed_at.get().copied()
+    }
+
+    fn shared_done(&self) -> Option<&OnceLock<SharedDone>> {
+        Some(&self.shared_done)
+    }
+
+    fn poll_detail(&mut self) -> Poll<(), ErrorDetail> {
+        if self.signal.is_fired() {
+            return self.canceled();
+        }
+
+        let result = self.parent.poll_detail()?;
+        if result == Async::NotReady {
+            // perform any necessary operations in order to get notified in case the context gets
+            // canceled
+            self.task.register();
+            if self.signal.is_fired() {
+                return self.canceled();
+            }
+        }
+        Ok(result)
+    }
+
+    fn poll_detail_ref(&self) -> Poll<(), ErrorDetail> {
+        if self.signal.is_fired() {
+            return self.canceled();
+        }
+
+        let result = self.parent.0.poll_detail_ref()?;
+        if result == Async::NotReady {
+            // the context may be polled by multiple tasks through shared references, which all
+            // need to be notified
+            self.signal.register();
+            if self.signal.is_fired() {
+                return self.canceled();
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl WithCancel {
+    fn canceled(&self) -> Poll<(), ErrorDetail> {
+        self.completed_at.get_or_init(Instant::now);
+        Err(ErrorDetail {
+            error: ContextError::Canceled,
+            source_kind: self.signal.source().unwrap_or(self.kind()),
+        })
     }
 }
 
@@ -20,28 +101,77 @@ impl Future for WithCancel {
     type Error = ContextError;
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        if *self.canceled.lock().unwrap() {
-            Err(ContextError::Canceled)
-        } else {
-            self.parent.0.poll().map(|r| {
-                if r == Async::NotReady {
-                    // perform any necessary operations in order to get notified in case the
-                    // context gets canceled
-                    let mut handle = self.handle.lock().unwrap();
-                    let must_update = match *handle {
-                        Some(ref task) if task.will_notify_current() => false,
-                        _ => true,
-                    };
-                    if must_update {
-                        *handle = Some(task::current())
-                    }
-                }
-                r
-            })
+        self.poll_detail().map_err(|detail| detail.error)
+    }
+}
+
+impl Drop for WithCancel {
+    fn drop(&mut self) {
+        if self.cancel_on_drop {
+            self.signal.fire();
         }
     }
 }
 
+/// A top-level context that is done from the start (see `already_done`).
+pub struct AlreadyDone {
+    signal: Signal,
+}
+
+impl InnerContext for AlreadyDone {
+    fn signal(&self) -> Option<&Signal> {
+        Some(&self.signal)
+    }
+
+    fn kind(&self) -> &'static str {
+        "already_done"
+    }
+
+    fn poll_detail(&mut self) -> Poll<(), ErrorDetail> {
+        self.poll_detail_ref()
+    }
+
+    fn poll_detail_ref(&self) -> Poll<(), ErrorDetail> {
+        Err(ErrorDetail {
+            error: self.signal.error().unwrap_or(ContextError::Canceled),
+            source_kind: self.kind(),
+        })
+    }
+}
+
+impl Future for AlreadyDone {
+    type Item = ();
+    type Error = ContextError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.poll_detail().map_err(|detail| detail.error)
+    }
+}
+
+/// Returns a top-level context that is already done with the given error, e.g. to test how code
+/// handles a dead context without having to wait for a deadline to expire.
+///
+/// # Example
+///
+/// ```
+/// extern crate ctx;
+/// extern crate futures;
+///
+/// use ctx::{already_done, ContextError};
+/// use futures::future::Future;
+///
+/// fn main() {
+///     let ctx = already_done(ContextError::DeadlineExceeded);
+///     assert_eq!(ctx.terminal_reason(), Some(ContextError::DeadlineExceeded));
+///     assert_eq!(ctx.wait().unwrap_err(), ContextError::DeadlineExceeded);
+/// }
+/// ```
+pub fn already_done(err: ContextError) -> Context {
+    let signal = Signal::new();
+    signal.fire_with(err);
+    Context::new(AlreadyDone { signal })
+}
+
 /// Returns a copy of parent as a new future, which is closed when the returned cancel function is
 /// called or when the parent context's future is resolved – whichever happens first.
 ///
@@ -61,36 +191,56 @@ impl Future for WithCancel {
 ///     assert_eq!(ctx.wait().unwrap_err(), ContextError::Canceled);
 /// }
 /// ```
-pub fn with_cancel(parent: Context) -> (Context, Box<Fn() + Send>) {
-    let canceled = Arc::new(Mutex::new(false));
-    let handle = Arc::new(Mutex::new(None));
-    let canceled_clone = canceled.clone();
-    let handle_clone = handle.clone();
-
-    let ctx = WithCancel {
-        parent: parent,
-        canceled: canceled,
-        handle: handle,
-    };
-    let cancel = Box::new(move || {
-        let mut canceled = canceled_clone.lock().unwrap();
-        *canceled = true;
-
-        if let Some(ref task) = *handle_clone.lock().unwrap() {
-            task.notify();
-        }
-    });
+pub fn with_cancel(parent: Context) -> (Context, CancelFn) {
+    let signal = Signal::new();
+    let ctx = WithCancel::new(parent, signal.clone(), false);
+    let cancel = Box::new(move || signal.fire());
+    (Context::new(ctx), cancel)
+}
+
+/// Same as `with_cancel`, but the returned cancel function records the given source (e.g.
+/// `"middleware"`), which is reported as the `source_kind` of the resulting `ErrorDetail` and by
+/// `Context::cancel_source`, also for all contexts derived from the returned one. This helps to
+/// find out which level of a chain of cancelable contexts originated a cancelation.
+pub fn with_cancel_from(parent: Context, source: &'static str) -> (Context, CancelFn) {
+    let signal = Signal::new();
+    let ctx = WithCancel::new(parent, signal.clone(), false);
+    let cancel = Box::new(move || signal.fire_from(source));
     (Context::new(ctx), cancel)
 }
 
+/// Returns a copy of parent as a new future, which is canceled once the returned context is
+/// dropped, instead of by calling a cancel function.
+///
+/// This changes the ownership semantics compared to `with_cancel`: the lifetime of the work
+/// associated with the context is bound to the context handle itself, so nothing observing the
+/// context (e.g. via `Context::done`) can outlive it. Note that contexts derived from the returned
+/// context take ownership of it, so it is only dropped once all of them are dropped.
+pub fn with_cancel_drop(parent: Context) -> Context {
+    Context::new(WithCancel::new(parent, Signal::new(), true))
+}
+
+/// Same as `with_cancel`, but additionally returns a `Done` future, which allows the creator of
+/// the context to observe its cancelation (e.g. to run cleanup), no matter whether it has been
+/// triggered by the returned cancel function, by a parent context or by a deadline.
+pub fn with_cancel_observed(parent: Context) -> (Context, CancelFn, Done) {
+    let (ctx, cancel) = with_cancel(parent);
+    let done = ctx.done();
+    (ctx, cancel, done)
+}
+
 #[cfg(test)]
 mod test {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
     use std::time::Duration;
     use std::thread;
     use tokio_timer::Timer;
-    use with_cancel::with_cancel;
-    use {background, ContextError};
-    use futures::Future;
+    use with_cancel::{already_done, with_cancel, with_cancel_drop, with_cancel_from,
+                      with_cancel_observed};
+    use {background, with_value, ContextError};
+    use futures::{Async, Future};
+    use futures::executor::{self, Notify, NotifyHandle};
 
     #[test]
     fn cancel_test() {
@@ -109,6 +259,18 @@ mod test {
         assert_eq!(ctx.wait().unwrap_err(), ContextError::Canceled);
     }
 
+    #[test]
+    fn cancel_on_drop_test() {
+        let ctx = with_cancel_drop(background());
+        let done = ctx.done();
+        let waiter = thread::spawn(move || done.wait());
+
+        thread::sleep(Duration::from_millis(50));
+        drop(ctx);
+
+        assert_eq!(waiter.join().unwrap().unwrap_err(), ContextError::Canceled);
+    }
+
     #[test]
     fn example_test() {
         let timer = Timer::default();
@@ -129,7 +291,89 @@ mod test {
         assert!(result.is_err());
         match result {
             Err((err, _)) => assert_eq!(err, ContextError::Canceled),
-            _ => assert!(false),
+            _ => panic!("expected the context to be canceled"),
         }
     }
+
+    #[test]
+    fn cancel_observed_test() {
+        let (ctx, cancel, done) = with_cancel_observed(background());
+        let observer = thread::spawn(move || done.wait());
+
+        thread::sleep(Duration::from_millis(50));
+        cancel();
+
+        assert_eq!(observer.join().unwrap().unwrap_err(), ContextError::Canceled);
+        assert_eq!(ctx.wait().unwrap_err(), ContextError::Canceled);
+    }
+
+    #[test]
+    fn cancel_observed_parent_test() {
+        let (parent, cancel) = with_cancel(background());
+        let (_ctx, _, done) = with_cancel_observed(parent);
+        cancel();
+
+        assert_eq!(done.wait().unwrap_err(), ContextError::Canceled);
+    }
+
+    #[test]
+    fn already_done_test() {
+        let mut ctx = with_value(already_done(ContextError::DeadlineExceeded), 42);
+        assert_eq!(ctx.poll(), Err(ContextError::DeadlineExceeded));
+        assert_eq!(ctx.terminal_reason(), Some(ContextError::DeadlineExceeded));
+        assert!(!ctx.is_canceled());
+        assert_eq!(ctx.done().wait(), Err(ContextError::DeadlineExceeded));
+
+        let ctx = already_done(ContextError::Canceled);
+        assert!(ctx.is_canceled());
+        assert_eq!(ctx.wait(), Err(ContextError::Canceled));
+    }
+
+    #[test]
+    fn cancel_source_test() {
+        let (root, cancel_root) = with_cancel_from(background(), "root");
+        let (middleware, cancel_middleware) = with_cancel_from(root, "middleware");
+        let (mut handler, _) = with_cancel_from(middleware, "handler");
+        assert_eq!(handler.cancel_source(), None);
+
+        cancel_root();
+        cancel_middleware();
+        assert_eq!(handler.cancel_source(), Some("middleware"));
+        assert_eq!(handler.poll_detail().unwrap_err().source_kind, "middleware");
+
+        let (root, cancel_root) = with_cancel_from(background(), "root");
+        let (middleware, _) = with_cancel_from(root, "middleware");
+        let (handler, _) = with_cancel(middleware);
+        let mut handler = with_value(handler, 42);
+        cancel_root();
+        assert_eq!(handler.cancel_source(), Some("root"));
+        assert_eq!(handler.poll_detail().unwrap_err().source_kind, "root");
+    }
+
+    #[derive(Default)]
+    struct Flag(AtomicBool);
+
+    impl Notify for Flag {
+        fn notify(&self, _id: usize) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn migrate_task_test() {
+        let (mut ctx, cancel) = with_cancel(background());
+        let first = Arc::new(Flag::default());
+        let second = Arc::new(Flag::default());
+
+        let result = executor::spawn(&mut ctx)
+            .poll_future_notify(&NotifyHandle::from(first.clone()), 0);
+        assert_eq!(result, Ok(Async::NotReady));
+        let result = executor::spawn(&mut ctx)
+            .poll_future_notify(&NotifyHandle::from(second.clone()), 0);
+        assert_eq!(result, Ok(Async::NotReady));
+
+        cancel();
+        assert!(second.0.load(Ordering::SeqCst));
+        assert!(!first.0.load(Ordering::SeqCst));
+    }
 }
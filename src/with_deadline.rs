@@ -1,21 +1,144 @@
+use std::sync::{Mutex, OnceLock};
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, Instant};
-use {Context, InnerContext, ContextError, with_cancel};
+use {Context, InnerContext, CancelFn, ContextError, ErrorDetail, SharedDone, Signal,
+     with_cancel};
 use futures::{Future, Poll, Async};
-use tokio_timer::{Timer, Sleep};
+use tokio_timer::TimerError;
+use timer::{schedule, SharedDeadline};
+use lock::lock;
+
+/// The maximum timeout in nanoseconds (see `set_max_timeout`), `u64::MAX` meaning unlimited.
+static MAX_TIMEOUT: AtomicU64 = AtomicU64::new(u64::MAX);
+
+/// Sets a process-global cap for all timeouts created via `with_timeout`, `with_deadline` and
+/// their variants: larger timeouts (or deadlines further in the future) are clamped to `max`.
+/// This is meant as a safety guard for servers, e.g. to enforce that no request may run for
+/// longer than 60s. Pass `Duration::MAX` to remove the cap again.
+///
+/// The cap is applied when a context is created, i.e. existing contexts are not affected.
+pub fn set_max_timeout(max: Duration) {
+    let nanos = u64::try_from(max.as_nanos()).unwrap_or(u64::MAX);
+    MAX_TIMEOUT.store(nanos, Ordering::SeqCst);
+}
+
+/// Clamps the given timeout to the cap set via `set_max_timeout`.
+fn capped(timeout: Duration) -> Duration {
+    match MAX_TIMEOUT.load(Ordering::SeqCst) {
+        u64::MAX => timeout,
+        max => timeout.min(Duration::from_nanos(max)),
+    }
+}
 
 pub struct WithDeadline {
     parent: Context,
     when: Instant,
-    deadline: Sleep,
+    timeout: Duration,
+    /// The sum of all active extensions of the deadline (see `Context::extend_deadline`).
+    extension: Mutex<Duration>,
+    /// The timer, together with the deadline it is currently set for.
+    deadline: Mutex<(Instant, SharedDeadline)>,
+    completed_at: OnceLock<Instant>,
+    shared_done: OnceLock<SharedDone>,
 }
 
 impl InnerContext for WithDeadline {
     fn deadline(&self) -> Option<Instant> {
-        Some(self.when)
+        Some(self.when + *lock(&self.extension))
+    }
+
+    fn extend_deadline(&self, extra: Duration) -> bool {
+        *lock(&self.extension) += extra;
+        true
+    }
+
+    fn revert_deadline_extension(&self, extra: Duration) {
+        {
+            let mut extension = lock(&self.extension);
+            *extension = extension.saturating_sub(extra);
+        }
+        // tasks waiting for the extended deadline must re-arm the timer
+        lock(&self.deadline).1.notify();
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        Some(self.timeout)
     }
 
     fn parent(&self) -> Option<&Context> {
-        self.parent.0.parent()
+        Some(&self.parent)
+    }
+
+    fn kind(&self) -> &'static str {
+        "with_deadline"
+    }
+
+    fn completed_at(&self) -> Option<Instant> {
+        self.completed_at.get().copied()
+    }
+
+    fn shared_done(&self) -> Option<&OnceLock<SharedDone>> {
+        Some(&self.shared_done)
+    }
+
+    fn poll_detail(&mut self) -> Poll<(), ErrorDetail> {
+        if self.canceled() {
+            // cancelation takes precedence over the deadline
+            return self.parent.poll_detail();
+        }
+        match self.expired(self.poll_timer()) {
+            Some(detail) => Err(detail),
+            None => self.parent.poll_detail(),
+        }
+    }
+
+    fn poll_detail_ref(&self) -> Poll<(), ErrorDetail> {
+        if self.canceled() {
+            return self.parent.0.poll_detail_ref();
+        }
+        match self.expired(self.poll_timer()) {
+            Some(detail) => Err(detail),
+            None => self.parent.0.poll_detail_ref(),
+        }
+    }
+
+    fn poll_deadline(&self) -> Poll<(), ContextError> {
+        match self.expired(self.poll_timer()) {
+            Some(detail) => Err(detail.error),
+            None => self.parent.0.poll_deadline(),
+        }
+    }
+}
+
+impl WithDeadline {
+    /// Returns whether the node itself has been canceled via the cancel function returned
+    /// alongside it, whose signal lives in the parent node (see `new_deadline`).
+    fn canceled(&self) -> bool {
+        self.parent.0.signal().is_some_and(Signal::is_fired)
+    }
+
+    /// Polls the timer, after setting it to the current (possibly extended) deadline.
+    fn poll_timer(&self) -> Poll<(), TimerError> {
+        let when = self.when + *lock(&self.extension);
+        let mut deadline = lock(&self.deadline);
+        if deadline.0 != when {
+            *deadline = (when, SharedDeadline::new(when));
+        }
+        deadline.1.poll()
+    }
+
+    fn expired(&self, poll: Poll<(), TimerError>) -> Option<ErrorDetail> {
+        let error = match poll {
+            Ok(Async::Ready(_)) => ContextError::DeadlineExceeded,
+            Ok(Async::NotReady) => return None,
+            Err(_) => ContextError::DeadlineTooLong,
+        };
+        self.completed_at.get_or_init(Instant::now);
+        Some(ErrorDetail {
+            error,
+            source_kind: self.kind(),
+        })
     }
 }
 
@@ -24,16 +147,12 @@ impl Future for WithDeadline {
     type Error = ContextError;
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        match self.deadline.poll() {
-            Ok(Async::Ready(_)) => Err(ContextError::DeadlineExceeded),
-            Ok(Async::NotReady) => self.parent.poll(),
-            Err(_) => Err(ContextError::DeadlineTooLong),
-        }
+        self.poll_detail().map_err(|detail| detail.error)
     }
 }
 
 /// Returns `with_timeout(parent, deadline - Instant::now())`.
-pub fn with_deadline(parent: Context, deadline: Instant) -> (Context, Box<Fn()>) {
+pub fn with_deadline(parent: Context, deadline: Instant) -> (Context, CancelFn) {
     with_timeout(parent, deadline - Instant::now())
 }
 
@@ -59,13 +178,236 @@ pub fn with_deadline(parent: Context, deadline: Instant) -> (Context, Box<Fn()>)
 ///     assert_eq!(ctx.wait().unwrap_err(), ContextError::DeadlineExceeded);
 /// }
 /// ```
-pub fn with_timeout(parent: Context, timeout: Duration) -> (Context, Box<Fn()>) {
-    let timer = Timer::default();
+pub fn with_timeout(parent: Context, timeout: Duration) -> (Context, CancelFn) {
+    let (ctx, cancel, _) = with_timeout_at(parent, timeout);
+    (ctx, cancel)
+}
+
+/// Same as `with_timeout`, but additionally returns the effective deadline of the returned
+/// context, which is the earlier of the newly created deadline and the parent's deadline.
+pub fn with_timeout_at(parent: Context, timeout: Duration) -> (Context, CancelFn, Instant) {
+    anchored(parent, timeout, Instant::now())
+}
+
+/// Same as `with_timeout`, but the timeout starts at the given anchor time instead of now (if
+/// given), e.g. to create predictable deadlines in tests together with `root_at`.
+///
+/// # Example
+///
+/// ```
+/// use std::time::{Duration, Instant};
+/// use ctx::{root_at, with_timeout_anchored};
+///
+/// let created = Instant::now();
+/// let root = root_at(created);
+/// let (ctx, _) = with_timeout_anchored(root, Duration::from_secs(10), Some(created));
+/// assert_eq!(ctx.deadline(), Some(created + Duration::from_secs(10)));
+/// ```
+pub fn with_timeout_anchored(
+    parent: Context,
+    timeout: Duration,
+    anchor: Option<Instant>,
+) -> (Context, CancelFn) {
+    let (ctx, cancel, _) = anchored(parent, timeout, anchor.unwrap_or_else(Instant::now));
+    (ctx, cancel)
+}
+
+fn anchored(parent: Context, timeout: Duration, anchor: Instant) -> (Context, CancelFn, Instant) {
+    let timeout = capped(timeout);
+    let when = anchor + timeout;
+    let effective = parent.deadline().map_or(when, |deadline| deadline.min(when));
+    let (ctx, cancel) = new_deadline(parent, when, timeout, SharedDeadline::new(when));
+    (ctx, cancel, effective)
+}
+
+/// Same as `with_timeout`, but the deadline is rounded up to the next multiple of `granularity`,
+/// and all contexts whose deadlines are rounded to the same instant share a single timer entry
+/// and fire together. Under high load, this reduces the number of timer registrations
+/// considerably.
+///
+/// This trades precision for efficiency: the deadline may be up to `granularity` later than
+/// requested (in addition to the timer's own resolution of 100ms). The rounded deadline is
+/// reported by `Context::deadline`.
+pub fn with_coalesced_timeout(
+    parent: Context,
+    timeout: Duration,
+    granularity: Duration,
+) -> (Context, CancelFn) {
+    let timeout = capped(timeout);
+    let (deadline, when) = SharedDeadline::coalesced(Instant::now() + timeout, granularity);
+    new_deadline(parent, when, timeout, deadline)
+}
+
+fn new_deadline(
+    parent: Context,
+    when: Instant,
+    timeout: Duration,
+    deadline: SharedDeadline,
+) -> (Context, CancelFn) {
     let (parent, cancel) = with_cancel(parent);
     let ctx = WithDeadline {
-        parent: parent,
-        when: Instant::now() + timeout,
-        deadline: timer.sleep(timeout),
+        parent,
+        when,
+        timeout,
+        extension: Mutex::new(Duration::ZERO),
+        deadline: Mutex::new((when, deadline)),
+        completed_at: OnceLock::new(),
+        shared_done: OnceLock::new(),
+    };
+    (Context::new(ctx), cancel)
+}
+
+/// Returns `with_timeout(parent, timeout)` if the resulting deadline is sooner than the parent's
+/// effective deadline. Otherwise, the parent is returned as is, together with a no-op cancel
+/// function. This guarantees that deadlines only ever shrink when deriving contexts.
+pub fn tighten_deadline(parent: Context, timeout: Duration) -> (Context, CancelFn) {
+    match parent.deadline() {
+        Some(deadline) if deadline <= Instant::now() + timeout => (parent, Box::new(|| {})),
+        _ => with_timeout(parent, timeout),
+    }
+}
+
+/// Same as `with_timeout(parent, hard)`, but additionally calls `on_soft` once the `soft` timeout
+/// elapses before the context is done, e.g. to start shedding load or to return partial results
+/// before the hard deadline cancels the context.
+///
+/// `on_soft` is called even if nobody polls the context at that time. It runs on the timer's
+/// shared scheduler thread and should thus return quickly (e.g. by setting a flag).
+pub fn with_soft_deadline<F>(
+    parent: Context,
+    soft: Duration,
+    hard: Duration,
+    on_soft: F,
+) -> (Context, CancelFn)
+where
+    F: 'static + FnOnce() + Send,
+{
+    let (ctx, cancel) = with_timeout(parent, hard);
+    let observed = ctx.downgrade();
+    schedule(Instant::now() + soft, move || {
+        // cancelation (and the hard deadline) take precedence
+        if let Some(ctx) = observed.upgrade() {
+            if ctx.terminal_reason().is_none() {
+                on_soft();
+            }
+        }
+    });
+    (ctx, cancel)
+}
+
+pub struct WithMappedDeadline {
+    parent: Context,
+    when: Option<Instant>,
+    signals: Vec<Signal>,
+    deadline: Option<SharedDeadline>,
+}
+
+impl InnerContext for WithMappedDeadline {
+    fn deadline(&self) -> Option<Instant> {
+        self.when
+    }
+
+    fn inherits_deadline(&self) -> bool {
+        false
+    }
+
+    fn parent(&self) -> Option<&Context> {
+        Some(&self.parent)
+    }
+
+    fn kind(&self) -> &'static str {
+        "with_mapped_deadline"
+    }
+
+    fn poll_deadline(&self) -> Poll<(), ContextError> {
+        match self.deadline.as_ref().map(SharedDeadline::poll) {
+            Some(Ok(Async::Ready(()))) => Err(ContextError::DeadlineExceeded),
+            Some(Err(_)) => Err(ContextError::DeadlineTooLong),
+            _ => Ok(Async::NotReady),
+        }
+    }
+
+    fn poll_detail(&mut self) -> Poll<(), ErrorDetail> {
+        self.poll_detail_ref()
+    }
+
+    fn poll_detail_ref(&self) -> Poll<(), ErrorDetail> {
+        for signal in &self.signals {
+            signal.register();
+        }
+        let result = self.poll_deadline();
+        if let Some(detail) = self.parent_canceled() {
+            return Err(detail);
+        }
+        if let Err(error) = result {
+            let source_kind = self.kind();
+            return Err(ErrorDetail { error, source_kind });
+        }
+
+        // ancestors without a signal are only observed by polling them; their deadlines are
+        // replaced by the node's own one though
+        match self.parent.0.poll_detail_ref() {
+            Err(detail) if !is_deadline_error(detail.error) => Err(detail),
+            _ => Ok(Async::NotReady),
+        }
+    }
+}
+
+impl WithMappedDeadline {
+    /// Returns the detail of the closest ancestor whose signal fired.
+    fn parent_canceled(&self) -> Option<ErrorDetail> {
+        self.parent.signals().find_map(|(ctx, signal)| {
+            signal.error().map(|error| ErrorDetail {
+                error,
+                source_kind: signal.source().unwrap_or(ctx.0.kind()),
+            })
+        })
+    }
+}
+
+fn is_deadline_error(error: ContextError) -> bool {
+    error == ContextError::DeadlineExceeded || error == ContextError::DeadlineTooLong
+}
+
+impl Future for WithMappedDeadline {
+    type Item = ();
+    type Error = ContextError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.poll_detail_ref().map_err(|detail| detail.error)
+    }
+}
+
+/// Returns a copy of the parent context, whose deadline is replaced by `f(parent_deadline)`, e.g.
+/// to always leave a tenth of the remaining time for cleanup. Contrary to `with_deadline`, the
+/// resulting deadline may also be later than the parent's one, and returning `None` removes the
+/// deadline for the returned context and all contexts derived from it. Cancelation (and other
+/// errors except for exceeded deadlines) of the parent is still propagated, reporting the kind of
+/// the parent node it originated from.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use ctx::{map_deadline, with_timeout, background};
+///
+/// let (parent, _) = with_timeout(background(), Duration::from_secs(10));
+/// let deadline = parent.deadline().unwrap();
+/// let (ctx, _) = map_deadline(parent, |when| when.map(|when| when - Duration::from_secs(1)));
+/// assert_eq!(ctx.deadline(), Some(deadline - Duration::from_secs(1)));
+/// ```
+pub fn map_deadline<F>(parent: Context, f: F) -> (Context, CancelFn)
+where
+    F: FnOnce(Option<Instant>) -> Option<Instant>,
+{
+    let when = f(parent.deadline());
+    let (parent, cancel) = with_cancel(parent);
+    let signals = parent.signals().map(|(_, signal)| signal.clone()).collect();
+    let ctx = WithMappedDeadline {
+        parent,
+        when,
+        signals,
+        deadline: when.map(SharedDeadline::new),
     };
     (Context::new(ctx), cancel)
 }
@@ -75,9 +417,102 @@ mod test {
     use std::time::{Instant, Duration};
     use std::thread;
     use tokio_timer::Timer;
-    use with_deadline::with_timeout;
-    use {background, ContextError, with_value};
-    use futures::Future;
+    use std::sync::{Arc, Mutex};
+    use with_deadline::{map_deadline, set_max_timeout, tighten_deadline, with_coalesced_timeout,
+                        with_deadline, with_soft_deadline, with_timeout, with_timeout_anchored,
+                        with_timeout_at};
+    use {background, merge_all, root_at, ContextError, with_cancel, with_value};
+    use futures::{Async, Future};
+    use futures::future::poll_fn;
+    use lock::lock;
+
+    #[test]
+    fn poll_deadline_test() {
+        let (ctx, cancel) = with_timeout(background(), Duration::from_millis(300));
+        let (mut ctx, _) = with_cancel(ctx);
+        cancel();
+
+        let result = poll_fn(|| Ok::<_, ()>(Async::Ready(ctx.poll_deadline()))).wait();
+        assert_eq!(result, Ok(Ok(Async::NotReady)));
+        assert_eq!(poll_fn(|| ctx.poll_deadline()).wait(), Err(ContextError::DeadlineExceeded));
+
+        let (ctx, _) = map_deadline(background(), |_| Some(Instant::now()));
+        let mut ctx = with_value(ctx, 42);
+        assert_eq!(poll_fn(|| ctx.poll_deadline()).wait(), Err(ContextError::DeadlineExceeded));
+
+        let mut ctx = background();
+        let result = poll_fn(|| Ok::<_, ()>(Async::Ready(ctx.poll_deadline()))).wait();
+        assert_eq!(result, Ok(Ok(Async::NotReady)));
+    }
+
+    #[test]
+    fn anchored_timeout_test() {
+        let created = Instant::now() - Duration::from_secs(1);
+        let root = root_at(created);
+        assert_eq!(root.created_at(), Some(created));
+
+        let anchor = root.created_at();
+        let (ctx, _) = with_timeout_anchored(root, Duration::from_secs(10), anchor);
+        assert_eq!(ctx.deadline(), Some(created + Duration::from_secs(10)));
+        assert_eq!(ctx.timeout_bucket(&[Duration::from_secs(10)]), Some(0));
+
+        let (ctx, _) = with_timeout_anchored(background(), Duration::from_secs(10), None);
+        assert!(ctx.deadline_is_about(Duration::from_secs(10), Duration::from_millis(50)));
+    }
+
+    // the cap is global, so it is only ever set to a value above all other timeouts used in tests
+    #[test]
+    fn max_timeout_test() {
+        set_max_timeout(Duration::from_secs(60));
+        let (ctx, _) = with_timeout(background(), Duration::from_secs(120));
+        let later = Instant::now() + Duration::from_secs(120);
+        let (deadline_ctx, _) = with_deadline(background(), later);
+        let (short_ctx, _) = with_timeout(background(), Duration::from_secs(10));
+        set_max_timeout(Duration::MAX);
+
+        assert!(ctx.deadline_is_about(Duration::from_secs(60), Duration::from_millis(50)));
+        assert!(deadline_ctx.deadline_is_about(Duration::from_secs(60), Duration::from_millis(50)));
+        assert!(short_ctx.deadline_is_about(Duration::from_secs(10), Duration::from_millis(50)));
+
+        let (ctx, _) = with_timeout(background(), Duration::from_secs(120));
+        assert!(ctx.deadline_is_about(Duration::from_secs(120), Duration::from_millis(50)));
+    }
+
+    #[test]
+    fn extend_deadline_test() {
+        let (ctx, _) = with_timeout(background(), Duration::from_secs(1));
+        let child = with_value(ctx.clone(), 42);
+        let tolerance = Duration::from_millis(50);
+        {
+            let _extension = ctx.extend_deadline(Duration::from_secs(1));
+            assert!(ctx.deadline_is_about(Duration::from_secs(2), tolerance));
+            assert!(child.deadline_is_about(Duration::from_secs(2), tolerance));
+        }
+        assert!(ctx.deadline_is_about(Duration::from_secs(1), tolerance));
+
+        let extension = background().extend_deadline(Duration::from_secs(1));
+        drop(extension);
+    }
+
+    #[test]
+    fn extend_deadline_poll_test() {
+        let (mut ctx, _) = with_timeout(background(), Duration::from_millis(200));
+        let extension = ctx.extend_deadline(Duration::from_secs(10));
+        let waiter = {
+            let ctx = ctx.clone();
+            thread::spawn(move || ctx.wait())
+        };
+
+        thread::sleep(Duration::from_millis(400));
+        let result = poll_fn(|| Ok::<_, ()>(Async::Ready(ctx.poll()))).wait();
+        assert_eq!(result, Ok(Ok(Async::NotReady)));
+
+        let reverted_at = Instant::now();
+        drop(extension);
+        assert_eq!(waiter.join().unwrap(), Err(ContextError::DeadlineExceeded));
+        assert!(reverted_at.elapsed() < Duration::from_secs(1));
+        assert_eq!(ctx.wait(), Err(ContextError::DeadlineExceeded));
+    }
 
     #[test]
     fn cancel_test() {
@@ -117,6 +552,100 @@ mod test {
         assert_eq!(ctx.wait().unwrap_err(), ContextError::DeadlineExceeded);
     }
 
+    #[test]
+    fn deadline_on_child_test() {
+        let (parent, _) = with_timeout(background(), Duration::from_millis(50));
+        let when = parent.deadline();
+        let ctx = with_value(parent, 42);
+
+        assert_eq!(ctx.deadline(), when);
+    }
+
+    #[test]
+    fn with_timeout_at_test() {
+        let (ctx, _, when) = with_timeout_at(background(), Duration::from_millis(50));
+        assert_eq!(ctx.deadline().unwrap(), when);
+
+        let (parent, _) = with_timeout(background(), Duration::from_millis(50));
+        let (ctx, _, when) = with_timeout_at(parent, Duration::from_secs(10));
+        assert_eq!(ctx.deadline().unwrap(), when);
+    }
+
+    #[test]
+    fn tighten_deadline_test() {
+        let (parent, _) = with_timeout(background(), Duration::from_millis(500));
+        let when = parent.deadline().unwrap();
+
+        let (ctx, _) = tighten_deadline(parent, Duration::from_secs(10));
+        assert_eq!(ctx.deadline().unwrap(), when);
+
+        let (ctx, _) = tighten_deadline(ctx, Duration::from_millis(50));
+        assert!(ctx.deadline().unwrap() < when);
+
+        let (ctx, _) = tighten_deadline(background(), Duration::from_millis(50));
+        assert!(ctx.deadline().is_some());
+    }
+
+    #[test]
+    fn deadline_future_test() {
+        let start = Instant::now();
+        let (ctx, cancel) = with_timeout(background(), Duration::from_millis(200));
+        cancel();
+
+        let deadline = ctx.deadline_future().unwrap();
+        assert_eq!(deadline.wait().unwrap_err(), ContextError::DeadlineExceeded);
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn deadline_future_background_test() {
+        assert!(background().deadline_future().is_none());
+    }
+
+    #[test]
+    fn poll_detail_test() {
+        let (parent, cancel) = with_cancel(background());
+        let (mut ctx, _) = with_timeout(parent, Duration::from_millis(50));
+
+        thread::sleep(Duration::from_millis(100));
+        cancel();
+
+        let detail = poll_fn(|| ctx.poll_detail()).wait().unwrap_err();
+        assert_eq!(detail.error, ContextError::DeadlineExceeded);
+        assert_eq!(detail.source_kind, "with_deadline");
+    }
+
+    #[test]
+    fn poll_detail_parent_test() {
+        let (parent, cancel) = with_cancel(background());
+        let (ctx, _) = with_timeout(parent, Duration::from_secs(2));
+        let mut ctx = with_value(ctx, 42);
+        cancel();
+
+        let detail = poll_fn(|| ctx.poll_detail()).wait().unwrap_err();
+        assert_eq!(detail.error, ContextError::Canceled);
+        assert_eq!(detail.source_kind, "with_cancel");
+    }
+
+    #[test]
+    fn cancel_precedence_test() {
+        let (ctx, cancel) = with_timeout(background(), Duration::from_millis(50));
+        let (nested, _) = with_timeout(ctx.clone(), Duration::from_millis(50));
+        thread::sleep(Duration::from_millis(300));
+        cancel();
+
+        // the node's own cancelation takes precedence over its own deadline
+        let detail = poll_fn(|| ctx.0.poll_detail_ref()).wait().unwrap_err();
+        assert_eq!(detail.error, ContextError::Canceled);
+        assert_eq!(ctx.clone().wait(), Err(ContextError::Canceled));
+
+        // the node's own deadline takes precedence over the cancelation of its parent
+        let detail = poll_fn(|| nested.0.poll_detail_ref()).wait().unwrap_err();
+        assert_eq!(detail.error, ContextError::DeadlineExceeded);
+        assert_eq!(nested.wait(), Err(ContextError::DeadlineExceeded));
+    }
+
     #[test]
     fn example_test() {
         let timer = Timer::default();
@@ -132,7 +661,121 @@ mod test {
         assert!(result.is_err());
         match result {
             Err((err, _)) => assert_eq!(err, ContextError::DeadlineExceeded),
-            _ => assert!(false),
+            _ => panic!("expected the deadline to be exceeded"),
         }
     }
+
+    #[test]
+    fn completed_at_test() {
+        let (ctx, _) = with_timeout(background(), Duration::from_millis(50));
+        let when = ctx.deadline().unwrap();
+        assert_eq!(ctx.completed_at(), None);
+
+        let mut ctx = with_value(ctx, 42);
+        thread::sleep(Duration::from_millis(200));
+        let result = poll_fn(|| Ok::<_, ()>(Async::Ready(ctx.poll()))).wait().unwrap();
+        assert_eq!(result, Err(ContextError::DeadlineExceeded));
+        let completed_at = ctx.completed_at().unwrap();
+        assert!(completed_at >= when);
+
+        // the first error is recorded
+        thread::sleep(Duration::from_millis(10));
+        let _ = poll_fn(|| Ok::<_, ()>(Async::Ready(ctx.poll()))).wait();
+        assert_eq!(ctx.completed_at(), Some(completed_at));
+    }
+
+    #[test]
+    fn map_deadline_shorten_test() {
+        let (parent, _) = with_timeout(background(), Duration::from_secs(10));
+        let (ctx, _) = map_deadline(parent, |when| {
+            when.map(|when| when - Duration::from_millis(9950))
+        });
+        assert!(ctx.deadline_remaining().unwrap() <= Duration::from_millis(50));
+        assert_eq!(ctx.wait(), Err(ContextError::DeadlineExceeded));
+    }
+
+    #[test]
+    fn map_deadline_remove_test() {
+        let (parent, cancel) = with_cancel(background());
+        let (parent, _) = with_timeout(parent, Duration::from_millis(50));
+        let (ctx, _) = map_deadline(parent, |_| None);
+        let mut ctx = with_value(ctx, 42);
+        assert_eq!(ctx.deadline(), None);
+
+        thread::sleep(Duration::from_millis(200));
+        let result = poll_fn(|| Ok::<_, ()>(Async::Ready(ctx.poll()))).wait().unwrap();
+        assert_eq!(result, Ok(Async::NotReady));
+
+        cancel();
+        let detail = poll_fn(|| ctx.poll_detail()).wait().unwrap_err();
+        assert_eq!(detail.error, ContextError::Canceled);
+        assert_eq!(detail.source_kind, "with_cancel");
+        assert_eq!(ctx.wait(), Err(ContextError::Canceled));
+    }
+
+    #[test]
+    fn map_deadline_merged_test() {
+        // the second merged context is not among the ancestors, and thus only observed by polling
+        let (secondary, cancel) = with_cancel(background());
+        let (ctx, _) = map_deadline(merge_all(vec![background(), secondary]), |_| None);
+        cancel();
+
+        let detail = poll_fn(|| ctx.0.poll_detail_ref()).wait().unwrap_err();
+        assert_eq!(detail.error, ContextError::Canceled);
+        assert_eq!(detail.source_kind, "with_cancel");
+    }
+
+    #[test]
+    fn soft_deadline_test() {
+        let soft_at = Arc::new(Mutex::new(None));
+        let (ctx, _) = {
+            let soft_at = soft_at.clone();
+            with_soft_deadline(
+                background(),
+                Duration::from_millis(50),
+                Duration::from_millis(300),
+                move || *lock(&soft_at) = Some(Instant::now()),
+            )
+        };
+
+        assert_eq!(ctx.wait(), Err(ContextError::DeadlineExceeded));
+        let hard_at = Instant::now();
+        let soft_at = lock(&soft_at).expect("soft callback not called");
+        assert!(soft_at < hard_at);
+    }
+
+    #[test]
+    fn soft_deadline_canceled_test() {
+        let called = Arc::new(Mutex::new(false));
+        let (_ctx, cancel) = {
+            let called = called.clone();
+            with_soft_deadline(
+                background(),
+                Duration::from_millis(300),
+                Duration::from_secs(1),
+                move || *lock(&called) = true,
+            )
+        };
+        cancel();
+
+        thread::sleep(Duration::from_millis(500));
+        assert!(!*lock(&called));
+    }
+
+    #[test]
+    fn coalesced_timeout_test() {
+        let granularity = Duration::from_millis(200);
+        let requested = Instant::now() + Duration::from_millis(100);
+        let (a, _) = with_coalesced_timeout(background(), Duration::from_millis(100), granularity);
+        let (b, _) = with_coalesced_timeout(background(), Duration::from_millis(101), granularity);
+
+        let window = requested + granularity + Duration::from_millis(10);
+        for ctx in &[&a, &b] {
+            let when = ctx.deadline().unwrap();
+            assert!(when >= requested && when < window);
+        }
+
+        assert_eq!(a.wait(), Err(ContextError::DeadlineExceeded));
+        assert_eq!(b.wait(), Err(ContextError::DeadlineExceeded));
+    }
 }
@@ -0,0 +1,203 @@
+use std::any::{type_name, Any, TypeId};
+use std::mem;
+use std::sync::{Mutex, OnceLock};
+use std::thread::{self, ThreadId};
+use {Context, InnerContext, ContextError, ErrorDetail};
+use futures::{Future, Poll};
+use lock::lock;
+use with_value::check_value;
+
+pub struct WithLazyValue<V, F> {
+    parent: Context,
+    init: Mutex<Init<F>>,
+    val: OnceLock<V>,
+}
+
+enum Init<F> {
+    Pending(F),
+    /// The initializer is currently running on the given thread.
+    Running(ThreadId),
+    Done,
+    /// The initializer panicked.
+    Poisoned,
+}
+
+/// Marks the initialization as done once dropped, or as poisoned if dropped while unwinding from
+/// a panicking initializer.
+struct Finish<'a, F: 'a>(&'a Mutex<Init<F>>);
+
+impl<'a, F> Drop for Finish<'a, F> {
+    fn drop(&mut self) {
+        *lock(self.0) = if thread::panicking() {
+            Init::Poisoned
+        } else {
+            Init::Done
+        };
+    }
+}
+
+impl<V, F> InnerContext for WithLazyValue<V, F>
+where
+    V: Any + Send + Sync,
+    F: FnOnce() -> V + Send,
+{
+    fn value(&self) -> Option<&dyn Any> {
+        self.val.get().map(|val| val as &dyn Any)
+    }
+
+    fn value_of(&self, type_id: TypeId) -> Option<&dyn Any> {
+        if type_id != TypeId::of::<V>() {
+            return None;
+        }
+        if let Some(val) = self.val.get() {
+            return Some(val as &dyn Any);
+        }
+        if let Init::Running(id) = *lock(&self.init) {
+            // waiting for the initialization would deadlock
+            if id == thread::current().id() {
+                panic!("lazy value of type {} looked up by its own initializer", type_name::<V>());
+            }
+        }
+
+        let val = self.val.get_or_init(|| {
+            let running = Init::Running(thread::current().id());
+            let f = match mem::replace(&mut *lock(&self.init), running) {
+                Init::Pending(f) => f,
+                _ => panic!("initializer of lazy value of type {} panicked", type_name::<V>()),
+            };
+            let _finish = Finish(&self.init);
+            f()
+        });
+        Some(val as &dyn Any)
+    }
+
+    fn value_type(&self) -> Option<TypeId> {
+        Some(TypeId::of::<V>())
+    }
+
+    fn value_type_name(&self) -> Option<&'static str> {
+        Some(type_name::<V>())
+    }
+
+    fn parent(&self) -> Option<&Context> {
+        Some(&self.parent)
+    }
+
+    fn kind(&self) -> &'static str {
+        "with_lazy_value"
+    }
+
+    fn poll_detail(&mut self) -> Poll<(), ErrorDetail> {
+        self.parent.poll_detail()
+    }
+}
+
+impl<V, F> Future for WithLazyValue<V, F>
+where
+    V: Any + Send + Sync,
+    F: FnOnce() -> V + Send,
+{
+    type Item = ();
+    type Error = ContextError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.parent.poll_inner()
+    }
+}
+
+/// Returns a copy of parent with a value associated to it, which is computed by calling `f` on
+/// the first lookup of a value of type `V`. Subsequent lookups return the cached value.
+///
+/// This is useful for request-scoped values that are expensive to compute but not always needed.
+///
+/// If `f` panics, the panic is propagated to the lookup and all subsequent lookups of the value
+/// panic as well. Looking up the value from within `f` panics too (instead of deadlocking).
+///
+/// # Examples
+///
+/// ```
+/// use ctx::{with_lazy_value, background};
+///
+/// let ctx = with_lazy_value(background(), || String::from("claims"));
+/// assert_eq!(ctx.value(), Some(String::from("claims")));
+/// ```
+pub fn with_lazy_value<V, F>(parent: Context, f: F) -> Context
+where
+    V: Any + Send + Sync,
+    F: 'static + FnOnce() -> V + Send,
+{
+    check_value::<V>(&parent);
+    Context::new(WithLazyValue {
+        parent,
+        init: Mutex::new(Init::Pending(f)),
+        val: OnceLock::new(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use lock::lock;
+    use with_lazy_value::with_lazy_value;
+    use {background, with_cancel, with_value, Context, ContextError};
+    use futures::Future;
+
+    #[test]
+    fn lazy_value_test() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = calls.clone();
+        let ctx = with_lazy_value(background(), move || {
+            calls_clone.fetch_add(1, Ordering::SeqCst);
+            42
+        });
+        let ctx = with_value(ctx, 1.0);
+
+        assert_eq!(ctx.value::<String>(), None);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        assert_eq!(ctx.value(), Some(42));
+        assert_eq!(ctx.value_ref(), Some(&42));
+        assert_eq!(ctx.value(), Some(42));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn poll_parent_test() {
+        let (parent, cancel) = with_cancel(background());
+        let ctx = with_lazy_value(parent, || 42);
+        cancel();
+
+        assert_eq!(ctx.wait().unwrap_err(), ContextError::Canceled);
+    }
+
+    fn panic_message(ctx: &Context) -> String {
+        let err = panic::catch_unwind(AssertUnwindSafe(|| ctx.value::<i32>())).unwrap_err();
+        match err.downcast::<String>() {
+            Ok(msg) => *msg,
+            Err(err) => err.downcast_ref::<&str>().unwrap().to_string(),
+        }
+    }
+
+    #[test]
+    fn panicking_init_test() {
+        let ctx = with_lazy_value(background(), || -> i32 { panic!("unavailable") });
+        assert_eq!(panic_message(&ctx), "unavailable");
+        assert!(panic_message(&ctx).contains("panicked"));
+    }
+
+    #[test]
+    fn reentrant_init_test() {
+        let slot: Arc<Mutex<Option<Context>>> = Arc::new(Mutex::new(None));
+        let inner = slot.clone();
+        let ctx = with_lazy_value(background(), move || {
+            let ctx = lock(&inner).clone().unwrap();
+            ctx.value::<i32>().unwrap_or(0) + 1
+        });
+        *lock(&slot) = Some(ctx.clone());
+
+        assert!(panic_message(&ctx).contains("own initializer"));
+        lock(&slot).take();
+    }
+}
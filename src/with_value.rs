@@ -1,38 +1,61 @@
-use std::any::Any;
-use {Context, InnerContext, ContextError};
+use std::any::{type_name, Any};
+use std::sync::Arc;
+use {Context, InnerContext, ContextError, ErrorDetail};
 use futures::{Future, Poll};
 
 pub struct WithValue<V>
 where
-    V: Any,
+    V: Any + Send + Sync,
 {
     parent: Context,
     val: V,
 }
 
+impl<V> WithValue<V>
+where
+    V: Any + Send + Sync,
+{
+    /// Creates the node without checking for shadowed values.
+    pub(crate) fn new(parent: Context, val: V) -> Self {
+        WithValue { parent, val }
+    }
+}
+
 impl<V> InnerContext for WithValue<V>
 where
-    V: Any,
+    V: Any + Send + Sync,
 {
-    fn value(&self) -> Option<&Any> {
-        let val_any = &self.val as &Any;
+    fn value(&self) -> Option<&dyn Any> {
+        let val_any = &self.val as &dyn Any;
         Some(val_any)
     }
 
+    fn value_type_name(&self) -> Option<&'static str> {
+        Some(type_name::<V>())
+    }
+
     fn parent(&self) -> Option<&Context> {
         Some(&self.parent)
     }
+
+    fn kind(&self) -> &'static str {
+        "with_value"
+    }
+
+    fn poll_detail(&mut self) -> Poll<(), ErrorDetail> {
+        self.parent.poll_detail()
+    }
 }
 
 impl<V> Future for WithValue<V>
 where
-    V: Any,
+    V: Any + Send + Sync,
 {
     type Item = ();
     type Error = ContextError;
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        self.parent.0.poll()
+        self.parent.poll_inner()
     }
 }
 
@@ -47,6 +70,13 @@ where
 /// retrieving a value. Having values of the same data type among the ancestors
 /// would always return the first hit.
 ///
+/// In debug builds, a warning is printed to stderr when the given value shadows a value of the
+/// same type already present among the ancestors. This can be turned off by disabling the
+/// `shadow-warning` feature.
+///
+/// With the `strict-values` feature and strict mode enabled (see `set_strict_values`), this
+/// panics in debug builds if `V` has not been declared via `declare_context_value!`.
+///
 /// # Examples
 ///
 /// ```
@@ -58,28 +88,191 @@ where
 /// assert_eq!(b.value(), Some(1.0));
 /// ```
 pub fn with_value<V>(parent: Context, val: V) -> Context
+where
+    V: Any + Send + Sync,
+{
+    check_value::<V>(&parent);
+    Context::new(WithValue::new(parent, val))
+}
+
+/// Runs the checks of `with_value` (see there) for a value of type `V` about to be associated
+/// to a copy of parent.
+#[cfg_attr(not(all(debug_assertions, feature = "shadow-warning")), allow(unused_variables))]
+pub(crate) fn check_value<V>(parent: &Context)
+where
+    V: Any,
+{
+    #[cfg(feature = "strict-values")]
+    ::strict::check_declared::<V>();
+
+    #[cfg(all(debug_assertions, feature = "shadow-warning"))]
+    {
+        if shadows::<V>(parent) {
+            warn_shadowed(type_name::<V>());
+        }
+    }
+}
+
+#[cfg(all(debug_assertions, feature = "shadow-warning"))]
+fn warn_shadowed(type_name: &'static str) {
+    #[cfg(test)]
+    test::WARNINGS.with(|warnings| warnings.borrow_mut().push(type_name));
+
+    eprintln!("ctx: with_value shadows an existing context value of type `{}`", type_name);
+}
+
+/// Same as `with_value`, but converts the given value into `V` first, e.g. to store a `&str` as a
+/// `String`.
+///
+/// # Examples
+///
+/// ```
+/// use ctx::{with_value_into, background};
+///
+/// let ctx = with_value_into::<String, _>(background(), "foo");
+/// assert_eq!(ctx.value(), Some(String::from("foo")));
+/// ```
+pub fn with_value_into<V, U>(parent: Context, val: U) -> Context
+where
+    V: Any + Send + Sync,
+    U: Into<V>,
+{
+    with_value(parent, val.into())
+}
+
+/// Same as `with_value`, but additionally returns whether the new value shadows a value of the
+/// same type already visible from the parent.
+///
+/// # Examples
+///
+/// ```
+/// use ctx::{set_value_checked, background};
+///
+/// let (a, shadowed) = set_value_checked(background(), 1);
+/// assert!(!shadowed);
+/// let (_, shadowed) = set_value_checked(a, 2);
+/// assert!(shadowed);
+/// ```
+pub fn set_value_checked<V>(parent: Context, val: V) -> (Context, bool)
+where
+    V: Any + Send + Sync,
+{
+    let shadowed = parent.value_ref::<V>().is_some();
+    (with_value(parent, val), shadowed)
+}
+
+/// Returns a copy of parent with the result of applying `f` to the parent's closest value of type
+/// `T` associated to it. The original value remains unchanged for the parent. If there is no value
+/// of type `T`, the parent is returned as is.
+///
+/// # Examples
+///
+/// ```
+/// use ctx::{map_value, with_value, background};
+///
+/// let ctx = with_value(background(), 42);
+/// let ctx = map_value(ctx, |n: i32| n.to_string());
+/// assert_eq!(ctx.value(), Some(String::from("42")));
+/// ```
+pub fn map_value<T, U, F>(parent: Context, f: F) -> Context
+where
+    T: Any + Clone,
+    U: Any + Send + Sync,
+    F: Fn(T) -> U,
+{
+    match parent.value::<T>() {
+        Some(val) => with_value(parent, f(val)),
+        None => parent,
+    }
+}
+
+/// Returns a copy of parent with the given shared value associated to it. Contrary to
+/// `with_value`, this does not require the value to be `Clone` in order to retrieve it, since it
+/// is retrieved by reference count via `Context::value_shared`.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use ctx::{with_value_arc, background};
+///
+/// struct Connection;
+///
+/// let ctx = with_value_arc(background(), Arc::new(Connection));
+/// assert!(ctx.value_shared::<Connection>().is_some());
+/// ```
+pub fn with_value_arc<V>(parent: Context, val: Arc<V>) -> Context
+where
+    V: Any + Send + Sync,
+{
+    with_value(parent, val)
+}
+
+impl Context {
+    /// Returns a shared handle to the closest value of type `T` that has been associated via
+    /// `with_value_arc`.
+    pub fn value_shared<T>(&self) -> Option<Arc<T>>
+    where
+        T: Any + Send + Sync,
+    {
+        self.value::<Arc<T>>()
+    }
+}
+
+/// Returns whether a value of type `V` is already associated to `parent` or any of its ancestors.
+/// Lazy values that have not been computed yet are not considered, to avoid computing them.
+#[cfg(all(debug_assertions, feature = "shadow-warning"))]
+fn shadows<V>(parent: &Context) -> bool
 where
     V: Any,
 {
-    Context::new(WithValue {
-        parent: parent,
-        val: val,
-    })
+    parent
+        .ancestors()
+        .any(|ctx| ctx.0.value().is_some_and(|val_any| val_any.is::<V>()))
 }
 
 #[cfg(test)]
 mod test {
-    use with_value::with_value;
+    use std::cell::RefCell;
+    use std::sync::Arc;
+    use with_value::{map_value, set_value_checked, with_value, with_value_arc,
+                     with_value_into};
     use with_cancel::with_cancel;
     use {background, ContextError};
     use futures::Future;
 
+    thread_local! {
+        /// The type names of the shadow warnings emitted on the current thread.
+        pub(super) static WARNINGS: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+    }
+
     #[test]
     fn value_ref() {
         let a = with_value(background(), 42);
         assert_eq!(a.value_ref(), Some(&42));
     }
 
+    #[test]
+    fn value_shared_test() {
+        struct Conn {
+            id: u32,
+        }
+
+        let conn = Arc::new(Conn { id: 7 });
+        let ctx = with_value_arc(background(), conn.clone());
+        let shared = ctx.value_shared::<Conn>().unwrap();
+        assert_eq!(shared.id, 7);
+        assert!(Arc::ptr_eq(&shared, &conn));
+        assert!(ctx.value_shared::<String>().is_none());
+    }
+
+    #[test]
+    fn value_into_test() {
+        let ctx = with_value_into::<String, _>(background(), "foo");
+        assert_eq!(ctx.value(), Some(String::from("foo")));
+        assert_eq!(ctx.value::<&str>(), None);
+    }
+
     #[test]
     fn poll_parent_test() {
         let (parent, cancel) = with_cancel(background());
@@ -114,4 +307,63 @@ mod test {
         let b = with_value(a, B(1));
         assert_eq!(b.value(), Some(A(1)));
     }
+
+    #[test]
+    fn map_value_test() {
+        let a = with_value(background(), 42);
+        let b = map_value(a, |n: i32| format!("#{}", n));
+        assert_eq!(b.value(), Some(String::from("#42")));
+        assert_eq!(b.value(), Some(42));
+
+        let c = map_value(background(), |n: i32| n.to_string());
+        assert_eq!(c.value::<String>(), None);
+    }
+
+    #[test]
+    fn set_value_checked_test() {
+        let a = with_value(background(), 42);
+        let (b, shadowed) = set_value_checked(a, 1.0);
+        assert!(!shadowed);
+
+        let (c, _) = with_cancel(b);
+        let (d, shadowed) = set_value_checked(c, 43);
+        assert!(shadowed);
+        assert_eq!(d.value(), Some(43));
+    }
+
+    #[test]
+    fn value_through_cancel_test() {
+        let a = with_value(background(), 42);
+        let (b, _) = with_cancel(a);
+        assert_eq!(b.value(), Some(42));
+        assert_eq!(b.value_ref(), Some(&42));
+    }
+
+    #[test]
+    #[cfg(all(debug_assertions, feature = "shadow-warning"))]
+    fn shadows_test() {
+        use with_value::shadows;
+
+        let a = with_value(background(), 42);
+        assert!(!shadows::<f64>(&a));
+        assert!(shadows::<i32>(&a));
+
+        let (b, _) = with_cancel(a);
+        assert!(shadows::<i32>(&b));
+    }
+
+    #[test]
+    #[cfg(all(debug_assertions, feature = "shadow-warning"))]
+    fn shadow_warning_test() {
+        use std::any::type_name;
+
+        WARNINGS.with(|warnings| warnings.borrow_mut().clear());
+        let a = with_value(background(), 42);
+        let (b, _) = with_cancel(with_value(a, 1.0));
+        assert!(WARNINGS.with(|warnings| warnings.borrow().is_empty()));
+
+        let c = with_value(b, 7);
+        assert_eq!(c.value(), Some(7));
+        WARNINGS.with(|warnings| assert_eq!(*warnings.borrow(), vec![type_name::<i32>()]));
+    }
 }
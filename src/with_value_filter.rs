@@ -0,0 +1,112 @@
+use std::any::TypeId;
+use {CancelFn, Context, InnerContext, ContextError, ErrorDetail, with_cancel};
+use futures::{Future, Poll};
+
+pub struct WithValueFilter {
+    parent: Context,
+    allowed: Vec<TypeId>,
+}
+
+impl InnerContext for WithValueFilter {
+    fn parent(&self) -> Option<&Context> {
+        Some(&self.parent)
+    }
+
+    fn forwards_value(&self, type_id: TypeId) -> bool {
+        self.allowed.contains(&type_id)
+    }
+
+    fn kind(&self) -> &'static str {
+        "with_value_filter"
+    }
+
+    fn poll_detail(&mut self) -> Poll<(), ErrorDetail> {
+        self.parent.poll_detail()
+    }
+}
+
+impl Future for WithValueFilter {
+    type Item = ();
+    type Error = ContextError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.parent.poll_inner()
+    }
+}
+
+/// Returns a copy of parent, which only exposes the parent's values whose types are contained in
+/// `allowed`. Cancelation and deadlines of the parent still propagate to the returned context.
+///
+/// This is useful to restrict the values passed across a security boundary.
+///
+/// # Example
+///
+/// ```
+/// use std::any::TypeId;
+/// use ctx::{with_value, with_value_filter, background};
+///
+/// let ctx = with_value(background(), 42);
+/// let ctx = with_value(ctx, "secret");
+/// let ctx = with_value_filter(ctx, &[TypeId::of::<i32>()]);
+/// assert_eq!(ctx.value(), Some(42));
+/// assert_eq!(ctx.value::<&str>(), None);
+/// ```
+pub fn with_value_filter(parent: Context, allowed: &[TypeId]) -> Context {
+    Context::new(WithValueFilter {
+        parent,
+        allowed: allowed.to_vec(),
+    })
+}
+
+/// Returns a cancelable copy of parent without any of the parent's values, e.g. when crossing a
+/// trust boundary within the same process. Cancelation and deadlines of the parent still
+/// propagate to the returned context. Same as `with_cancel(with_value_filter(parent, &[]))`.
+pub fn strip_values(parent: Context) -> (Context, CancelFn) {
+    with_cancel(with_value_filter(parent, &[]))
+}
+
+#[cfg(test)]
+mod test {
+    use std::any::TypeId;
+    use with_value_filter::{strip_values, with_value_filter};
+    use {background, with_cancel, with_value, ContextError};
+    use futures::Future;
+
+    #[test]
+    fn filter_test() {
+        let ctx = with_value(background(), 42);
+        let ctx = with_value(ctx, 1.0);
+        let ctx = with_value_filter(ctx, &[TypeId::of::<i32>()]);
+
+        assert_eq!(ctx.value(), Some(42));
+        assert_eq!(ctx.value::<f64>(), None);
+        assert_eq!(ctx.value_ref::<f64>(), None);
+    }
+
+    #[test]
+    fn values_below_filter_test() {
+        let ctx = with_value_filter(background(), &[]);
+        let ctx = with_value(ctx, 1.0);
+        assert_eq!(ctx.value(), Some(1.0));
+    }
+
+    #[test]
+    fn cancel_test() {
+        let (parent, cancel) = with_cancel(background());
+        let ctx = with_value_filter(parent, &[]);
+        cancel();
+
+        assert_eq!(ctx.wait().unwrap_err(), ContextError::Canceled);
+    }
+
+    #[test]
+    fn strip_values_test() {
+        let (parent, cancel) = with_cancel(background());
+        let parent = with_value(parent, 42);
+        let (ctx, _) = strip_values(parent);
+        assert_eq!(ctx.value::<i32>(), None);
+
+        cancel();
+        assert_eq!(ctx.wait().unwrap_err(), ContextError::Canceled);
+    }
+}
@@ -0,0 +1,181 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use {Context, InnerContext, ContextError, ErrorDetail, Signal};
+use futures::{Future, Poll, Async};
+
+pub struct WithWorkLimit {
+    parent: Context,
+    signal: Signal,
+}
+
+/// The handle used to account for units of work done on behalf of a context created via
+/// `with_work_limit`.
+#[derive(Clone)]
+pub struct WorkToken {
+    remaining: Arc<AtomicUsize>,
+    signal: Signal,
+}
+
+impl InnerContext for WithWorkLimit {
+    fn parent(&self) -> Option<&Context> {
+        Some(&self.parent)
+    }
+
+    fn signal(&self) -> Option<&Signal> {
+        Some(&self.signal)
+    }
+
+    fn kind(&self) -> &'static str {
+        "with_work_limit"
+    }
+
+    fn poll_detail(&mut self) -> Poll<(), ErrorDetail> {
+        if self.signal.is_fired() {
+            return Err(self.exhausted());
+        }
+
+        let result = self.parent.poll_detail()?;
+        if result == Async::NotReady {
+            self.signal.register();
+            if self.signal.is_fired() {
+                return Err(self.exhausted());
+            }
+        }
+        Ok(result)
+    }
+
+    fn poll_detail_ref(&self) -> Poll<(), ErrorDetail> {
+        if self.signal.is_fired() {
+            return Err(self.exhausted());
+        }
+
+        let result = self.parent.0.poll_detail_ref()?;
+        if result == Async::NotReady {
+            self.signal.register();
+            if self.signal.is_fired() {
+                return Err(self.exhausted());
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl WithWorkLimit {
+    fn exhausted(&self) -> ErrorDetail {
+        ErrorDetail {
+            error: ContextError::WorkExhausted,
+            source_kind: self.kind(),
+        }
+    }
+}
+
+impl Future for WithWorkLimit {
+    type Item = ();
+    type Error = ContextError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.poll_detail().map_err(|detail| detail.error)
+    }
+}
+
+impl WorkToken {
+    /// Accounts for one unit of work. Returns `false` and resolves the context with
+    /// `ContextError::WorkExhausted` once more units than allowed have been done.
+    pub fn tick(&self) -> bool {
+        let within_limit = self
+            .remaining
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        if !within_limit {
+            self.signal.fire_with(ContextError::WorkExhausted);
+        }
+        within_limit
+    }
+
+    /// Returns the number of units of work that can still be done.
+    pub fn remaining(&self) -> usize {
+        self.remaining.load(Ordering::SeqCst)
+    }
+}
+
+/// Returns a copy of parent, which additionally bounds the units of work done on its behalf. The
+/// returned token has to be ticked for each unit of work; once it has been ticked more than `n`
+/// times, the context resolves with `ContextError::WorkExhausted`.
+///
+/// This bounds compute instead of wall-clock time.
+///
+/// # Example
+///
+/// ```
+/// extern crate ctx;
+/// extern crate futures;
+///
+/// use ctx::{ContextError, with_work_limit, background};
+/// use futures::future::Future;
+///
+/// fn main() {
+///     let (ctx, token) = with_work_limit(background(), 1);
+///     assert!(token.tick());
+///     assert!(!token.tick());
+///
+///     assert_eq!(ctx.wait().unwrap_err(), ContextError::WorkExhausted);
+/// }
+/// ```
+pub fn with_work_limit(parent: Context, n: usize) -> (Context, WorkToken) {
+    let signal = Signal::new();
+    let ctx = WithWorkLimit {
+        parent,
+        signal: signal.clone(),
+    };
+    let token = WorkToken {
+        remaining: Arc::new(AtomicUsize::new(n)),
+        signal,
+    };
+    (Context::new(ctx), token)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use std::thread;
+    use with_work_limit::with_work_limit;
+    use {background, with_cancel, ContextError};
+    use futures::Future;
+
+    #[test]
+    fn work_limit_test() {
+        let (ctx, token) = with_work_limit(background(), 3);
+        let done = ctx.done();
+
+        for _ in 0..3 {
+            assert!(token.tick());
+        }
+        assert_eq!(token.remaining(), 0);
+        assert!(done.check().is_none());
+
+        assert!(!token.tick());
+        assert_eq!(done.wait().unwrap_err(), ContextError::WorkExhausted);
+        assert_eq!(ctx.wait().unwrap_err(), ContextError::WorkExhausted);
+    }
+
+    #[test]
+    fn wake_test() {
+        let (ctx, token) = with_work_limit(background(), 0);
+
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            token.tick();
+        });
+
+        assert_eq!(ctx.wait().unwrap_err(), ContextError::WorkExhausted);
+    }
+
+    #[test]
+    fn cancel_parent_test() {
+        let (parent, cancel) = with_cancel(background());
+        let (ctx, _token) = with_work_limit(parent, 1);
+        cancel();
+
+        assert_eq!(ctx.wait().unwrap_err(), ContextError::Canceled);
+    }
+}
@@ -0,0 +1,47 @@
+//! Strict mode is global to the process, which is why it is tested in its own test binary, where
+//! it cannot affect the tests of the library itself.
+#![cfg(all(feature = "strict-values", debug_assertions))]
+
+#[macro_use]
+extern crate ctx;
+
+use ctx::{set_strict_values, with_lazy_value, with_named_value, with_value, background};
+
+struct Declared;
+struct Undeclared;
+
+#[test]
+fn declared_test() {
+    declare_context_value!(Declared);
+    set_strict_values(true);
+    let ctx = with_value(background(), Declared);
+    assert!(ctx.value_ref::<Declared>().is_some());
+}
+
+#[test]
+#[should_panic(expected = "has not been declared")]
+fn with_value_test() {
+    set_strict_values(true);
+    with_value(background(), Undeclared);
+}
+
+#[test]
+#[should_panic(expected = "has not been declared")]
+fn with_lazy_value_test() {
+    set_strict_values(true);
+    with_lazy_value(background(), || Undeclared);
+}
+
+#[test]
+#[should_panic(expected = "has not been declared")]
+fn with_named_value_test() {
+    set_strict_values(true);
+    with_named_value(background(), "undeclared", Undeclared);
+}
+
+#[test]
+#[should_panic(expected = "has not been declared")]
+fn push_value_test() {
+    set_strict_values(true);
+    let _guard = background().push_value(Undeclared);
+}